@@ -9,6 +9,11 @@ use std::{
     task::Poll,
 };
 
+/// Request IDs of the `playback-time` requests made on `playback-restart`, which are turned into
+/// events instead of replies.
+const SEEKED: i64 = i64::MIN;
+const RESTARTED: i64 = i64::MIN + 1;
+
 pub(super) struct MpvIpcWorker {
     stream: UnixStream,
     requests: kanal::AsyncReceiver<(Command, oneshot::Sender<Result<serde_json::Value, String>>)>,
@@ -109,9 +114,13 @@ impl MpvIpcWorker {
                             Response::CommandResponseSuccess {
                                 data, request_id, ..
                             } => {
-                                if request_id == i64::MIN {
+                                if request_id == SEEKED || request_id == RESTARTED {
                                     if let Ok(playback_time) = serde_json::from_value(data) {
-                                        events.push(Event::Seeked { playback_time });
+                                        events.push(if request_id == SEEKED {
+                                            Event::Seeked { playback_time }
+                                        } else {
+                                            Event::Restarted { playback_time }
+                                        });
                                     }
                                 } else if let Some(sender) = requests.try_remove(request_id as _)
                                     && !sender.is_closed()
@@ -121,8 +130,8 @@ impl MpvIpcWorker {
                                 }
                             }
                             Response::CommandResponseFailure { request_id, error } => {
-                                if request_id == i64::MIN {
-                                    tracing::error!(error = %error, "Failed get playback-time after seek event");
+                                if request_id == SEEKED || request_id == RESTARTED {
+                                    tracing::error!(error = %error, "Failed get playback-time after playback-restart event");
                                 } else if let Some(sender) = requests.try_remove(request_id as _)
                                     && !sender.is_closed()
                                     && let Err(e) = sender.send(Err(error.into()))
@@ -133,12 +142,15 @@ impl MpvIpcWorker {
                             Response::Event(event) => {
                                 match event {
                                     Event::Seek => seeking = true,
-                                    Event::PlaybackRestart if seeking => {
-                                        seeking = false;
+                                    Event::PlaybackRestart => {
                                         let request = Request {
                                             command: ListCommand::GetProperty("playback-time")
                                                 .into(),
-                                            request_id: i64::MIN,
+                                            request_id: if mem::take(&mut seeking) {
+                                                SEEKED
+                                            } else {
+                                                RESTARTED
+                                            },
                                             r#async: Default::default(),
                                         };
                                         if let Err(e) =
@@ -2,7 +2,7 @@
 
 use compact_str::CompactString;
 use derive_deftly::{Deftly, derive_deftly_adhoc};
-use serde::{Deserialize, Serialize, Serializer, de::IgnoredAny, ser, ser::SerializeSeq};
+use serde::{Deserialize, Serialize, Serializer, ser, ser::SerializeSeq};
 use serde_constant::ConstBool;
 use serde_json::Value;
 use serde_variant::to_variant_name;
@@ -127,10 +127,31 @@ pub(crate) enum NamedCommand {
         #[serde(skip_serializing_if = "Option::is_none")]
         flags: Option<PlaylistFlags>,
     },
+    PlaylistPlayIndex {
+        index: u64,
+    },
+    PlaylistShuffle,
+    PlaylistUnshuffle,
+    PlaylistMove {
+        index1: u64,
+        index2: u64,
+    },
+    Stop {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        flags: Option<StopFlags>,
+    },
     Quit {
         #[serde(skip_serializing_if = "Option::is_none")]
         code: Option<i64>,
     },
+    ExpandText {
+        text: CompactString,
+    },
+    ScreenshotToFile {
+        filename: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        flags: Option<ScreenshotFlags>,
+    },
 }
 
 pub(crate) struct SeekFlags(
@@ -209,6 +230,14 @@ pub(crate) enum LoadFlags {
     InsertAtPlay,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ScreenshotFlags {
+    Subtitles,
+    Video,
+    Window,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) enum PlaylistFlags {
@@ -216,6 +245,12 @@ pub(crate) enum PlaylistFlags {
     Force,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum StopFlags {
+    KeepPlaylist,
+}
+
 #[derive(Deserialize)]
 #[serde(untagged)]
 pub(super) enum Response {
@@ -276,6 +311,11 @@ pub(crate) enum Event {
     Seeked {
         playback_time: Seconds,
     },
+    /// Playback restarted without a seek, e.g. after loading a file.
+    #[serde(skip_deserializing)]
+    Restarted {
+        playback_time: Seconds,
+    },
     #[serde(skip_deserializing)]
     Unknown(CompactString),
 }
@@ -303,10 +343,12 @@ pub(crate) enum Property {
 #[serde(tag = "name", content = "data", rename_all = "kebab-case")]
 pub(crate) enum KnownProperty {
     Fullscreen(#[serde(default)] Option<bool>),
+    Chapter(#[serde(default)] Option<i64>),
     PlaylistCurrentPos(#[serde(default)] Option<u64>),
     PlaylistCount(#[serde(default)] Option<u64>),
     Seekable(#[serde(default)] Option<bool>),
     IdleActive(#[serde(default)] Option<bool>),
+    CoreIdle(#[serde(default)] Option<bool>),
     EofReached(#[serde(default)] Option<bool>),
     Pause(#[serde(default)] Option<bool>),
     LoopFile(#[serde(default)] Option<LoopData>),
@@ -314,12 +356,19 @@ pub(crate) enum KnownProperty {
     Speed(#[serde(default)] Option<f64>),
     Shuffle(#[serde(default)] Option<bool>),
     Volume(#[serde(default)] Option<f64>),
+    VolumeMax(#[serde(default)] Option<f64>),
+    Mute(#[serde(default)] Option<bool>),
+    VoConfigured(#[serde(default)] Option<bool>),
+    #[serde(rename = "user-data/mpv-mpris2/stopped")]
+    Stopped(#[serde(default)] Option<bool>),
     Duration(#[serde(default)] Option<Seconds>),
     MediaTitle(#[serde(default)] Option<String>),
     Metadata(#[serde(default)] BTreeMap<MetadataKey, String>),
     TrackList(#[serde(default)] Vec<Track>),
     Path(#[serde(default)] Option<Path>),
     WorkingDirectory(#[serde(default)] Option<PathBuf>),
+    #[serde(rename = "user-data/mpv/ytdl/json-subprocess-result")]
+    YtdlJsonSubprocessResult(#[serde(default)] Option<SubprocessResult>),
 }
 
 #[derive(Debug, Deserialize)]
@@ -361,12 +410,21 @@ pub(crate) enum MetadataKey {
     Disc,
     Genre,
     Lyricist,
+    Title,
     Track,
+    #[strum(serialize = "icy-title")]
+    IcyTitle,
+    #[strum(serialize = "icy-name")]
+    IcyName,
+    #[strum(serialize = "icy-genre")]
+    IcyGenre,
+    #[strum(serialize = "icy-url")]
+    IcyUrl,
     #[strum(default)]
     Other(CompactString),
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub(crate) enum Track {
     #[serde(rename_all = "kebab-case")]
@@ -397,7 +455,61 @@ pub(crate) enum Track {
         external: ConstBool<true>,
         external_filename: PathBuf,
     },
-    None(IgnoredAny),
+    #[serde(rename_all = "kebab-case")]
+    Video {
+        r#type: TrackType,
+        image: ConstBool<false>,
+    },
+    /// Any other track, e.g. audio or subtitles.
+    Other {},
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TrackType {
+    Video,
+}
+
+/// `option-info/<name>`, only the bounds of numeric options.
+#[derive(Debug, Deserialize)]
+pub(crate) struct OptionInfo {
+    #[serde(default)]
+    pub(crate) min: Option<f64>,
+    #[serde(default)]
+    pub(crate) max: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PlaylistEntry {
+    pub(crate) filename: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubprocessResult {
+    #[serde(default)]
+    pub(crate) stdout: String,
+}
+
+/// The subset of the youtube-dl/yt-dlp JSON info dict that is shown as metadata.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct YtdlInfo {
+    pub(crate) original_url: Option<String>,
+    pub(crate) webpage_url: Option<String>,
+    pub(crate) uploader: Option<String>,
+    pub(crate) channel: Option<String>,
+    pub(crate) upload_date: Option<String>,
+    pub(crate) thumbnail: Option<String>,
+    pub(crate) thumbnails: Vec<YtdlThumbnail>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct YtdlThumbnail {
+    pub(crate) url: String,
+    pub(crate) width: Option<u64>,
+    pub(crate) height: Option<u64>,
+    pub(crate) preference: Option<i64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
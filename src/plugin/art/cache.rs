@@ -0,0 +1,114 @@
+use crate::plugin::options::Options;
+use std::{
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tempfile::NamedTempFile;
+use url::Url;
+
+/// Entries keep the suffix of their image format, so shells can sniff the type from the path.
+const SUFFIXES: [&str; 6] = [".jpg", ".png", ".gif", ".webp", ".avif", ".bmp"];
+
+/// Extracted art under `$XDG_CACHE_HOME/mpv-mpris2/art`, keyed by the identity of the source file.
+#[derive(Clone, Debug)]
+pub(super) struct Cache {
+    dir: PathBuf,
+    max_size: u64,
+    max_age: Duration,
+}
+
+impl Cache {
+    pub(super) fn new(options: &Options) -> Option<Self> {
+        if !options.art_cache {
+            return None;
+        }
+        let dir = super::cache_dir()?.join("mpv-mpris2/art");
+        Some(Self::in_dir(dir, options))
+    }
+
+    pub(super) fn in_dir(dir: PathBuf, options: &Options) -> Self {
+        Self {
+            dir,
+            max_size: options.art_cache_size.saturating_mul(1024 * 1024),
+            max_age: Duration::from_secs(options.art_cache_age.saturating_mul(24 * 60 * 60)),
+        }
+    }
+
+    pub(super) fn key(path: &Path, index: u64, normalize: &str) -> io::Result<String> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata.modified()?.duration_since(UNIX_EPOCH);
+        let mtime = mtime.unwrap_or_default().as_nanos();
+        let mut key = path.as_os_str().as_bytes().to_vec();
+        key.extend_from_slice(format!("\0{mtime}\0{}\0{index}\0", metadata.len()).as_bytes());
+        key.extend_from_slice(normalize.as_bytes());
+        Ok(format!("{:x}", md5::compute(key)))
+    }
+
+    /// Remote art has no identity besides its URL.
+    pub(super) fn remote_key(url: &Url, normalize: &str) -> String {
+        format!("{:x}", md5::compute(format!("{url}\0{normalize}")))
+    }
+
+    fn path(&self, key: &str, suffix: &str) -> PathBuf {
+        self.dir.join(format!("{key}{suffix}"))
+    }
+
+    pub(super) fn get(&self, key: &str) -> Option<PathBuf> {
+        SUFFIXES.iter().find_map(|suffix| {
+            let path = self.path(key, suffix);
+            let file = fs::File::open(&path).ok()?;
+            // eviction goes by mtime, so a hit keeps the entry alive
+            _ = file.set_modified(SystemTime::now());
+            Some(path)
+        })
+    }
+
+    pub(super) fn tempfile(&self) -> io::Result<NamedTempFile> {
+        fs::create_dir_all(&self.dir)?;
+        tempfile::Builder::new()
+            .prefix(".tmp-")
+            .tempfile_in(&self.dir)
+    }
+
+    pub(super) fn insert(
+        &self,
+        key: &str,
+        suffix: &str,
+        file: NamedTempFile,
+    ) -> io::Result<PathBuf> {
+        let path = self.path(key, suffix);
+        file.persist(&path)?;
+        Ok(path)
+    }
+
+    /// Removes entries older than the maximum age, then the least recently used entries until the
+    /// cache fits in the maximum size.
+    pub(super) fn evict(&self) -> io::Result<()> {
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() || entry.file_name().as_bytes().starts_with(b".tmp-") {
+                continue;
+            }
+            let mtime = metadata.modified()?;
+            if now.duration_since(mtime).unwrap_or_default() > self.max_age {
+                fs::remove_file(entry.path())?;
+            } else {
+                entries.push((mtime, metadata.len(), entry.path()));
+            }
+        }
+        entries.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let mut size = 0u64;
+        for (_, len, path) in entries {
+            size = size.saturating_add(len);
+            if size > self.max_size {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
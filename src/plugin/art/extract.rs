@@ -0,0 +1,900 @@
+//! Embedded cover extraction for the common containers, without decoding or re-encoding images.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    mem,
+    path::Path,
+};
+
+const MAX_SIZE: u64 = 64 * 1024 * 1024;
+const FRONT_COVER: u32 = 3;
+
+pub(super) struct Picture {
+    pub(super) mime: &'static str,
+    pub(super) data: Vec<u8>,
+}
+
+impl Picture {
+    /// Returns `None` for data that isn't a known image format.
+    pub(super) fn new(data: Vec<u8>) -> Option<Self> {
+        let mime = match data.as_slice() {
+            [0xff, 0xd8, 0xff, ..] => "image/jpeg",
+            [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => "image/png",
+            [b'G', b'I', b'F', b'8', ..] => "image/gif",
+            [
+                b'R',
+                b'I',
+                b'F',
+                b'F',
+                _,
+                _,
+                _,
+                _,
+                b'W',
+                b'E',
+                b'B',
+                b'P',
+                ..,
+            ] => "image/webp",
+            [
+                _,
+                _,
+                _,
+                _,
+                b'f',
+                b't',
+                b'y',
+                b'p',
+                b'a',
+                b'v',
+                b'i',
+                b'f',
+                ..,
+            ] => "image/avif",
+            [b'B', b'M', ..] => "image/bmp",
+            _ => return None,
+        };
+        Some(Self { mime, data })
+    }
+
+    pub(super) fn suffix(&self) -> &'static str {
+        suffix(self.mime)
+    }
+}
+
+pub(super) fn suffix(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => ".png",
+        "image/gif" => ".gif",
+        "image/webp" => ".webp",
+        "image/avif" => ".avif",
+        "image/bmp" => ".bmp",
+        _ => ".jpg",
+    }
+}
+
+/// Keeps the first picture, unless a later one is the front cover.
+#[derive(Default)]
+struct Pictures(Option<(bool, Picture)>);
+
+impl Pictures {
+    fn push(&mut self, front: bool, data: Vec<u8>) {
+        if self
+            .0
+            .as_ref()
+            .is_none_or(|&(current, _)| front && !current)
+            && let Some(picture) = Picture::new(data)
+        {
+            self.0 = Some((front, picture));
+        }
+    }
+
+    fn has_front(&self) -> bool {
+        matches!(self.0, Some((true, _)))
+    }
+}
+
+/// Returns the front cover, or the first picture, embedded in `path`.
+pub(super) fn extract(path: &Path) -> io::Result<Option<Picture>> {
+    let mut r = BufReader::new(fs::File::open(path)?);
+    let mut pictures = Pictures::default();
+    let mut start = 0;
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if magic.starts_with(b"ID3") {
+        start = id3v2(&mut r, &mut pictures)?;
+        if pictures.has_front() {
+            return Ok(pictures.0.map(|(_, picture)| picture));
+        }
+        r.seek(SeekFrom::Start(start))?;
+        r.read_exact(&mut magic)?;
+    }
+    match magic {
+        [b'f', b'L', b'a', b'C', ..] => flac(&mut r, start + 4, &mut pictures)?,
+        [b'O', b'g', b'g', b'S', ..] => ogg(&mut r, start, &mut pictures)?,
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => matroska(&mut r, start, &mut pictures)?,
+        [_, _, _, _, b'f', b't', b'y', b'p'] => mp4(&mut r, start, &mut pictures)?,
+        _ => (),
+    }
+    Ok(pictures.0.map(|(_, picture)| picture))
+}
+
+fn invalid() -> io::Error {
+    io::ErrorKind::InvalidData.into()
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_vec(r: &mut impl Read, len: u64) -> io::Result<Vec<u8>> {
+    if len > MAX_SIZE {
+        return Err(invalid());
+    }
+    let mut buf = vec![0; len as usize];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn be_uint(b: &[u8]) -> u64 {
+    b.iter().fold(0, |acc, &b| acc << 8 | u64::from(b))
+}
+
+fn le32(b: &[u8]) -> Option<usize> {
+    Some(u32::from_le_bytes(b.get(..4)?.try_into().ok()?) as usize)
+}
+
+fn syncsafe(b: &[u8]) -> u64 {
+    b.iter().fold(0, |acc, &b| acc << 7 | u64::from(b & 0x7f))
+}
+
+fn split_u32(b: &[u8]) -> Option<(u32, &[u8])> {
+    let (n, rest) = b.split_first_chunk::<4>()?;
+    Some((u32::from_be_bytes(*n), rest))
+}
+
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = 0;
+    for &b in data {
+        if !(prev == 0xff && b == 0) {
+            out.push(b);
+        }
+        prev = b;
+    }
+    out
+}
+
+/// Parses an ID3v2 tag after the `ID3` magic and returns the offset past its end.
+fn id3v2<R: Read + Seek>(r: &mut R, pictures: &mut Pictures) -> io::Result<u64> {
+    r.seek(SeekFrom::Start(3))?;
+    let [major, _, flags, size @ ..] = read_array::<7>(r)?;
+    let size = syncsafe(&size);
+    let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+    let end = 10 + size + footer;
+    let mut tag = read_vec(r, size)?;
+    if major < 4 && flags & 0x80 != 0 {
+        tag = resynchronise(&tag);
+    }
+    let mut pos = match (major, tag.get(..4)) {
+        (3, Some(len)) if flags & 0x40 != 0 => 4 + be_uint(len) as usize,
+        (4, Some(len)) if flags & 0x40 != 0 => syncsafe(len) as usize,
+        _ => 0,
+    };
+    let header_len = if major == 2 { 6 } else { 10 };
+    while let Some(header) = tag.get(pos..pos + header_len)
+        && header[0] != 0
+    {
+        let (id, size, frame_flags) = match major {
+            2 => (&header[..3], be_uint(&header[3..6]), 0),
+            3 => (&header[..4], be_uint(&header[4..8]), be_uint(&header[8..])),
+            _ => (&header[..4], syncsafe(&header[4..8]), be_uint(&header[8..])),
+        };
+        pos += header_len;
+        let Some(body) = tag.get(pos..pos + size as usize) else {
+            break;
+        };
+        pos += size as usize;
+        if !matches!(id, b"APIC" | b"PIC") {
+            continue;
+        }
+        let body = match major {
+            // compressed or encrypted
+            3 if frame_flags & 0x00c0 != 0 => continue,
+            4 if frame_flags & 0x000c != 0 => continue,
+            3 if frame_flags & 0x0020 != 0 => Cow::Borrowed(body.get(1..).unwrap_or_default()),
+            4 => {
+                let skip =
+                    (frame_flags & 0x0040 != 0) as usize + 4 * (frame_flags & 0x0001) as usize;
+                let body = body.get(skip..).unwrap_or_default();
+                if frame_flags & 0x0002 != 0 || flags & 0x80 != 0 {
+                    Cow::Owned(resynchronise(body))
+                } else {
+                    Cow::Borrowed(body)
+                }
+            }
+            _ => Cow::Borrowed(body),
+        };
+        if let Some((kind, data)) = apic(&body, major == 2) {
+            pictures.push(kind == FRONT_COVER, data.to_vec());
+        }
+    }
+    Ok(end)
+}
+
+fn apic(body: &[u8], v22: bool) -> Option<(u32, &[u8])> {
+    let (&encoding, rest) = body.split_first()?;
+    // the MIME type (or v2.2 image format) is ignored, the data is sniffed instead
+    let rest = if v22 {
+        rest.get(3..)?
+    } else {
+        let end = rest.iter().position(|&b| b == 0)?;
+        &rest[end + 1..]
+    };
+    let (&kind, rest) = rest.split_first()?;
+    let data = match encoding {
+        // UTF-16 descriptions end with two NUL bytes
+        1 | 2 => {
+            let end = rest.chunks_exact(2).position(|c| c == [0, 0])?;
+            &rest[end * 2 + 2..]
+        }
+        _ => {
+            let end = rest.iter().position(|&b| b == 0)?;
+            &rest[end + 1..]
+        }
+    };
+    Some((kind.into(), data))
+}
+
+fn flac<R: Read + Seek>(r: &mut R, start: u64, pictures: &mut Pictures) -> io::Result<()> {
+    r.seek(SeekFrom::Start(start))?;
+    loop {
+        let [header, len @ ..] = read_array::<4>(r)?;
+        let len = be_uint(&len);
+        match header & 0x7f {
+            4 => vorbis_comment(&read_vec(r, len)?, pictures),
+            6 => flac_picture(&read_vec(r, len)?, pictures),
+            _ => r.seek_relative(len as i64)?,
+        }
+        if header & 0x80 != 0 {
+            return Ok(());
+        }
+    }
+}
+
+fn flac_picture(block: &[u8], pictures: &mut Pictures) {
+    fn parse(block: &[u8]) -> Option<(u32, &[u8])> {
+        let (kind, rest) = split_u32(block)?;
+        let (len, rest) = split_u32(rest)?;
+        let rest = rest.get(len as usize..)?;
+        let (len, rest) = split_u32(rest)?;
+        let rest = rest.get(len as usize..)?;
+        // width, height, depth and colors
+        let rest = rest.get(16..)?;
+        let (len, rest) = split_u32(rest)?;
+        Some((kind, rest.get(..len as usize)?))
+    }
+    if let Some((kind, data)) = parse(block) {
+        pictures.push(kind == FRONT_COVER, data.to_vec());
+    }
+}
+
+fn vorbis_comment(block: &[u8], pictures: &mut Pictures) {
+    let Some(vendor) = le32(block) else {
+        return;
+    };
+    let Some(mut rest) = block.get(4 + vendor..) else {
+        return;
+    };
+    let Some(count) = le32(rest) else {
+        return;
+    };
+    rest = &rest[4..];
+    for _ in 0..count {
+        let Some(len) = le32(rest) else {
+            return;
+        };
+        let Some(comment) = rest.get(4..4 + len) else {
+            return;
+        };
+        rest = &rest[4 + len..];
+        let Some(eq) = comment.iter().position(|&b| b == b'=') else {
+            continue;
+        };
+        let (key, value) = (&comment[..eq], &comment[eq + 1..]);
+        if key.eq_ignore_ascii_case(b"METADATA_BLOCK_PICTURE") {
+            if let Ok(block) = STANDARD.decode(value) {
+                flac_picture(&block, pictures);
+            }
+        } else if key.eq_ignore_ascii_case(b"COVERART")
+            && let Ok(data) = STANDARD.decode(value)
+        {
+            pictures.push(false, data);
+        }
+    }
+}
+
+/// Reads the comment header, the second packet of the first logical stream.
+fn ogg<R: Read + Seek>(r: &mut R, start: u64, pictures: &mut Pictures) -> io::Result<()> {
+    r.seek(SeekFrom::Start(start))?;
+    let mut serial = None;
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    while packets.len() < 2 {
+        let header = read_array::<27>(r)?;
+        if !header.starts_with(b"OggS") {
+            return Err(invalid());
+        }
+        let segments = read_vec(r, header[26].into())?;
+        let len = segments.iter().map(|&len| u64::from(len)).sum();
+        if *serial.get_or_insert(header[14..18].to_vec()) != header[14..18] {
+            r.seek_relative(len as i64)?;
+            continue;
+        }
+        let body = read_vec(r, len)?;
+        let mut body = body.as_slice();
+        for &len in &segments {
+            let (segment, rest) = body.split_at(len.into());
+            packet.extend_from_slice(segment);
+            body = rest;
+            if len < 255 {
+                packets.push(mem::take(&mut packet));
+            }
+        }
+        if packet.len() as u64 > MAX_SIZE {
+            return Err(invalid());
+        }
+    }
+    let comment = &packets[1];
+    if let Some(comment) = comment
+        .strip_prefix(b"\x03vorbis")
+        .or_else(|| comment.strip_prefix(b"OpusTags"))
+    {
+        vorbis_comment(comment, pictures);
+    }
+    Ok(())
+}
+
+/// Returns the content offset and end offset of the first atom named `name` in `pos..end`.
+fn mp4_atom<R: Read + Seek>(
+    r: &mut R,
+    mut pos: u64,
+    end: u64,
+    name: &[u8; 4],
+) -> io::Result<Option<(u64, u64)>> {
+    while pos + 8 <= end {
+        r.seek(SeekFrom::Start(pos))?;
+        let header = read_array::<8>(r)?;
+        let (size, header_len) = match be_uint(&header[..4]) {
+            0 => (end - pos, 8),
+            1 => (u64::from_be_bytes(read_array(r)?), 16),
+            size => (size, 8),
+        };
+        if size < header_len {
+            return Err(invalid());
+        }
+        let next = pos
+            .checked_add(size)
+            .filter(|&next| next <= end)
+            .ok_or_else(invalid)?;
+        if header[4..] == *name {
+            return Ok(Some((pos + header_len, next)));
+        }
+        pos = next;
+    }
+    Ok(None)
+}
+
+fn mp4<R: Read + Seek>(r: &mut R, start: u64, pictures: &mut Pictures) -> io::Result<()> {
+    let end = r.seek(SeekFrom::End(0))?;
+    let mut range = (start, end);
+    for name in [b"moov", b"udta", b"meta", b"ilst", b"covr"] {
+        let Some((mut pos, end)) = mp4_atom(r, range.0, range.1, name)? else {
+            return Ok(());
+        };
+        // iTunes `meta` is a full box, QuickTime `meta` is not
+        if name == b"meta" && pos + 4 <= end {
+            r.seek(SeekFrom::Start(pos))?;
+            if read_array::<4>(r)? == [0; 4] {
+                pos += 4;
+            }
+        }
+        range = (pos, end);
+    }
+    let (mut pos, end) = range;
+    while let Some((start, next)) = mp4_atom(r, pos, end, b"data")? {
+        // type indicator and locale
+        r.seek(SeekFrom::Start(start + 8))?;
+        let data = read_vec(r, next.saturating_sub(start + 8))?;
+        pictures.push(false, data);
+        pos = next;
+    }
+    Ok(())
+}
+
+const EBML_SEGMENT: u32 = 0x1853_8067;
+const EBML_SEEK_HEAD: u32 = 0x114d_9b74;
+const EBML_SEEK: u32 = 0x4dbb;
+const EBML_SEEK_ID: u32 = 0x53ab;
+const EBML_SEEK_POSITION: u32 = 0x53ac;
+const EBML_CLUSTER: u32 = 0x1f43_b675;
+const EBML_ATTACHMENTS: u32 = 0x1941_a469;
+const EBML_ATTACHED_FILE: u32 = 0x61a7;
+const EBML_FILE_NAME: u32 = 0x466e;
+const EBML_FILE_MIME_TYPE: u32 = 0x4660;
+const EBML_FILE_DATA: u32 = 0x465c;
+
+fn ebml_id(r: &mut impl Read) -> io::Result<u32> {
+    let [first] = read_array(r)?;
+    let len = first.leading_zeros() + 1;
+    if len > 4 {
+        return Err(invalid());
+    }
+    let mut id = u32::from(first);
+    for _ in 1..len {
+        let [b] = read_array(r)?;
+        id = id << 8 | u32::from(b);
+    }
+    Ok(id)
+}
+
+/// Returns `None` for elements of unknown size.
+fn ebml_size(r: &mut impl Read) -> io::Result<Option<u64>> {
+    let [first] = read_array(r)?;
+    let len = first.leading_zeros() + 1;
+    if len > 8 {
+        return Err(invalid());
+    }
+    let mut size = u64::from(first) & (0xff >> len);
+    for _ in 1..len {
+        let [b] = read_array(r)?;
+        size = size << 8 | u64::from(b);
+    }
+    Ok((size != (1 << (7 * len)) - 1).then_some(size))
+}
+
+fn ebml_element(r: &mut impl Read) -> io::Result<(u32, u64)> {
+    let id = ebml_id(r)?;
+    let size = ebml_size(r)?.ok_or_else(invalid)?;
+    Ok((id, size))
+}
+
+fn matroska<R: Read + Seek>(r: &mut R, start: u64, pictures: &mut Pictures) -> io::Result<()> {
+    r.seek(SeekFrom::Start(start))?;
+    let (_, size) = ebml_element(r)?;
+    r.seek_relative(size as i64)?;
+    if ebml_id(r)? != EBML_SEGMENT {
+        return Ok(());
+    }
+    let segment_size = ebml_size(r)?;
+    let segment = r.stream_position()?;
+    let mut attachments = None;
+    while segment_size.is_none_or(|size| r.stream_position().is_ok_and(|pos| pos < segment + size))
+    {
+        let Ok(id) = ebml_id(r) else {
+            break;
+        };
+        let Some(size) = ebml_size(r)? else {
+            break;
+        };
+        match id {
+            EBML_ATTACHMENTS => return matroska_attachments(r, size, pictures),
+            EBML_SEEK_HEAD => {
+                let seek_head = read_vec(r, size)?;
+                attachments = attachments.or(matroska_seek_head(&seek_head, segment));
+            }
+            EBML_CLUSTER => break,
+            _ => r.seek_relative(size as i64)?,
+        }
+    }
+    // attachments are usually before the clusters, but may be at the end of the file
+    if let Some(pos) = attachments {
+        r.seek(SeekFrom::Start(pos))?;
+        let (id, size) = ebml_element(r)?;
+        if id == EBML_ATTACHMENTS {
+            matroska_attachments(r, size, pictures)?;
+        }
+    }
+    Ok(())
+}
+
+fn matroska_seek_head(seek_head: &[u8], segment: u64) -> Option<u64> {
+    let mut r = io::Cursor::new(seek_head);
+    while let Ok((id, size)) = ebml_element(&mut r) {
+        let seek = read_vec(&mut r, size).ok()?;
+        if id != EBML_SEEK {
+            continue;
+        }
+        let (mut seek_id, mut seek_position) = (None, None);
+        let mut seek = io::Cursor::new(seek);
+        while let Ok((id, size)) = ebml_element(&mut seek) {
+            let value = read_vec(&mut seek, size).ok()?;
+            match id {
+                EBML_SEEK_ID => seek_id = Some(be_uint(&value)),
+                EBML_SEEK_POSITION if value.len() <= 8 => seek_position = Some(be_uint(&value)),
+                EBML_SEEK_POSITION => return None,
+                _ => (),
+            }
+        }
+        if seek_id == Some(EBML_ATTACHMENTS.into()) {
+            return segment.checked_add(seek_position?);
+        }
+    }
+    None
+}
+
+/// Files named `cover.*` are front covers, see the Matroska attachments spec.
+fn matroska_attachments<R: Read + Seek>(
+    r: &mut R,
+    size: u64,
+    pictures: &mut Pictures,
+) -> io::Result<()> {
+    let end = r.stream_position()? + size;
+    while r.stream_position()? < end {
+        let (id, size) = ebml_element(r)?;
+        if id != EBML_ATTACHED_FILE {
+            r.seek_relative(size as i64)?;
+            continue;
+        }
+        let file_end = r.stream_position()? + size;
+        let (mut name, mut mime) = (Vec::new(), Vec::new());
+        while r.stream_position()? < file_end {
+            let (id, size) = ebml_element(r)?;
+            match id {
+                EBML_FILE_NAME => name = read_vec(r, size)?,
+                EBML_FILE_MIME_TYPE => mime = read_vec(r, size)?,
+                EBML_FILE_DATA if mime.starts_with(b"image/") => {
+                    let front = name.to_ascii_lowercase().starts_with(b"cover.");
+                    pictures.push(front, read_vec(r, size)?);
+                }
+                _ => r.seek_relative(size as i64)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const JPEG: &[u8] = b"\xff\xd8\xff\xe0jpeg";
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\npng";
+    /// Stands in for the audio after a tag.
+    const AUDIO: &[u8] = b"\xff\xfb\x90\x64\0\0\0\0\0\0\0\0";
+
+    fn extract_bytes(data: &[u8]) -> io::Result<Option<Picture>> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(data)?;
+        extract(file.path())
+    }
+
+    fn data(data: &[u8]) -> Option<Vec<u8>> {
+        extract_bytes(data).unwrap().map(|picture| picture.data)
+    }
+
+    fn syncsafe_bytes(n: usize) -> [u8; 4] {
+        [21, 14, 7, 0].map(|shift| (n >> shift) as u8 & 0x7f)
+    }
+
+    fn id3(major: u8, frames: &[Vec<u8>]) -> Vec<u8> {
+        let body = frames.concat();
+        let mut tag = vec![b'I', b'D', b'3', major, 0, 0];
+        tag.extend(syncsafe_bytes(body.len()));
+        tag.extend(body);
+        tag
+    }
+
+    fn apic_frame(major: u8, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut body = vec![0];
+        if major == 2 {
+            body.extend(b"JPG");
+        } else {
+            body.extend(b"image/jpeg\0");
+        }
+        body.push(kind);
+        body.extend(b"description\0");
+        body.extend(data);
+        let mut frame = Vec::new();
+        match major {
+            2 => {
+                frame.extend(b"PIC");
+                frame.extend(&(body.len() as u32).to_be_bytes()[1..]);
+            }
+            3 => {
+                frame.extend(b"APIC");
+                frame.extend((body.len() as u32).to_be_bytes());
+                frame.extend([0, 0]);
+            }
+            _ => {
+                frame.extend(b"APIC");
+                frame.extend(syncsafe_bytes(body.len()));
+                frame.extend([0, 0]);
+            }
+        }
+        frame.extend(body);
+        frame
+    }
+
+    #[test]
+    fn id3v2_front_cover() {
+        for major in [2, 3, 4] {
+            let frames = [apic_frame(major, 0, JPEG), apic_frame(major, 3, PNG)];
+            let file = [id3(major, &frames), AUDIO.to_vec()].concat();
+            assert_eq!(data(&file).as_deref(), Some(PNG), "ID3v2.{major}");
+        }
+    }
+
+    #[test]
+    fn id3v2_first_picture() {
+        let frames = [
+            b"TIT2\0\0\0\x06\0\0\0title".to_vec(),
+            apic_frame(3, 0, JPEG),
+            apic_frame(3, 4, PNG),
+        ];
+        let file = [id3(3, &frames), AUDIO.to_vec()].concat();
+        assert_eq!(data(&file).as_deref(), Some(JPEG));
+    }
+
+    #[test]
+    fn id3v2_unsynchronised() {
+        let frames = [apic_frame(3, 3, JPEG)];
+        let mut tag = id3(3, &frames);
+        tag[5] = 0x80;
+        // 0xff 0xd8 becomes 0xff 0x00 0xd8
+        let at = tag.windows(2).position(|w| w == [0xff, 0xd8]).unwrap();
+        tag.insert(at + 1, 0);
+        let size = tag.len() - 10;
+        tag[6..10].copy_from_slice(&syncsafe_bytes(size));
+        let file = [tag, AUDIO.to_vec()].concat();
+        assert_eq!(data(&file).as_deref(), Some(JPEG));
+    }
+
+    fn flac_picture_block(kind: u32, data: &[u8]) -> Vec<u8> {
+        let mut block = kind.to_be_bytes().to_vec();
+        block.extend(10u32.to_be_bytes());
+        block.extend(b"image/jpeg");
+        block.extend(0u32.to_be_bytes());
+        block.extend([0; 16]);
+        block.extend((data.len() as u32).to_be_bytes());
+        block.extend(data);
+        block
+    }
+
+    fn flac_block(kind: u8, last: bool, body: &[u8]) -> Vec<u8> {
+        let mut block = vec![kind | if last { 0x80 } else { 0 }];
+        block.extend(&(body.len() as u32).to_be_bytes()[1..]);
+        block.extend(body);
+        block
+    }
+
+    #[test]
+    fn flac() {
+        let file = [
+            b"fLaC".to_vec(),
+            flac_block(0, false, &[0; 34]),
+            flac_block(6, false, &flac_picture_block(0, JPEG)),
+            flac_block(6, true, &flac_picture_block(3, PNG)),
+        ]
+        .concat();
+        assert_eq!(data(&file).as_deref(), Some(PNG));
+    }
+
+    #[test]
+    fn flac_after_id3v2() {
+        let file = [
+            id3(3, &[]),
+            b"fLaC".to_vec(),
+            flac_block(6, true, &flac_picture_block(0, JPEG)),
+        ]
+        .concat();
+        assert_eq!(data(&file).as_deref(), Some(JPEG));
+    }
+
+    fn vorbis_comment_block(comments: &[&[u8]]) -> Vec<u8> {
+        let mut block = 6u32.to_le_bytes().to_vec();
+        block.extend(b"vendor");
+        block.extend((comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend((comment.len() as u32).to_le_bytes());
+            block.extend(*comment);
+        }
+        block
+    }
+
+    /// A page holding whole packets.
+    fn ogg_page(serial: u32, sequence: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut segments = Vec::new();
+        for packet in packets {
+            segments.extend(std::iter::repeat_n(255, packet.len() / 255));
+            segments.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS\0\x02".to_vec();
+        page.extend([0; 8]);
+        page.extend(serial.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        page.push(segments.len() as u8);
+        page.extend(segments);
+        page.extend(packets.concat());
+        page
+    }
+
+    #[test]
+    fn ogg_opus() {
+        let picture = STANDARD.encode(flac_picture_block(3, JPEG));
+        let comment = [b"METADATA_BLOCK_PICTURE=", picture.as_bytes()].concat();
+        let padding = [&b"COMMENT="[..], &[b'x'; 300]].concat();
+        let tags = [
+            b"OpusTags".to_vec(),
+            vorbis_comment_block(&[&padding, &comment]),
+        ]
+        .concat();
+        // a long comment header spans several segments
+        assert!(tags.len() > 255);
+        let file = [
+            ogg_page(1, 0, &[b"OpusHead\x01\x02\0\0\x80\xbb\0\0\0\0\0".to_vec()]),
+            ogg_page(2, 0, &[b"\x01vorbis other stream".to_vec()]),
+            ogg_page(1, 1, &[tags]),
+        ]
+        .concat();
+        assert_eq!(data(&file).as_deref(), Some(JPEG));
+    }
+
+    #[test]
+    fn ogg_vorbis_coverart() {
+        let coverart = [b"COVERART=", STANDARD.encode(PNG).as_bytes()].concat();
+        let tags = [
+            b"\x03vorbis".to_vec(),
+            vorbis_comment_block(&[&coverart]),
+            vec![1],
+        ]
+        .concat();
+        let file = [
+            ogg_page(1, 0, &[b"\x01vorbis\0\0\0\0".to_vec()]),
+            ogg_page(1, 1, &[tags, b"\x05vorbis setup".to_vec()]),
+        ]
+        .concat();
+        assert_eq!(data(&file).as_deref(), Some(PNG));
+    }
+
+    fn atom(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&(body.len() as u32 + 8).to_be_bytes(), &name[..], body].concat()
+    }
+
+    fn mp4_file(ilst: &[u8]) -> Vec<u8> {
+        let meta = [&[0; 4][..], &atom(b"hdlr", &[0; 25]), &atom(b"ilst", ilst)].concat();
+        let moov = [
+            atom(b"mvhd", &[0; 100]),
+            atom(b"udta", &atom(b"meta", &meta)),
+        ]
+        .concat();
+        [
+            atom(b"ftyp", b"M4A \0\0\0\0M4A mp42isom"),
+            atom(b"free", &[]),
+            atom(b"moov", &moov),
+            atom(b"mdat", &[0; 16]),
+        ]
+        .concat()
+    }
+
+    fn mp4_data(data: &[u8]) -> Vec<u8> {
+        atom(b"data", &[&[0, 0, 0, 13, 0, 0, 0, 0], data].concat())
+    }
+
+    #[test]
+    fn mp4() {
+        let covr = [mp4_data(JPEG), mp4_data(PNG)].concat();
+        let ilst = [atom(b"\xa9nam", &mp4_data(b"title")), atom(b"covr", &covr)].concat();
+        assert_eq!(data(&mp4_file(&ilst)).as_deref(), Some(JPEG));
+    }
+
+    #[test]
+    fn mp4_without_cover() {
+        let ilst = atom(b"\xa9nam", &mp4_data(b"title"));
+        assert_eq!(data(&mp4_file(&ilst)), None);
+    }
+
+    #[test]
+    fn mp4_largesize() {
+        for largesize in [u64::MAX, u64::MAX - 8, 1 << 40] {
+            let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+            file.extend(1u32.to_be_bytes());
+            file.extend(b"free");
+            file.extend(largesize.to_be_bytes());
+            file.extend(atom(b"moov", &[]));
+            let e = extract_bytes(&file).err();
+            assert_eq!(e.map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        }
+    }
+
+    fn ebml(id: u32, body: &[u8]) -> Vec<u8> {
+        let id = id.to_be_bytes();
+        let skip = id.iter().position(|&b| b != 0).unwrap_or(3);
+        let size = if body.len() < 127 {
+            vec![0x80 | body.len() as u8]
+        } else {
+            [&[0x01][..], &(body.len() as u64).to_be_bytes()[1..]].concat()
+        };
+        [&id[skip..], &size, body].concat()
+    }
+
+    fn attachment(name: &[u8], mime: &[u8], data: &[u8]) -> Vec<u8> {
+        let file = [
+            ebml(EBML_FILE_NAME, name),
+            ebml(EBML_FILE_MIME_TYPE, mime),
+            ebml(EBML_FILE_DATA, data),
+        ]
+        .concat();
+        ebml(EBML_ATTACHED_FILE, &file)
+    }
+
+    fn matroska_file(segment: &[u8]) -> Vec<u8> {
+        let header = ebml(0x1a45_dfa3, &ebml(0x4282, b"matroska"));
+        [header, ebml(EBML_SEGMENT, segment)].concat()
+    }
+
+    #[test]
+    fn matroska_attachments() {
+        let attachments = [
+            attachment(b"font.ttf", b"font/ttf", b"font"),
+            attachment(b"small_cover.png", b"image/png", PNG),
+            attachment(b"cover.jpg", b"image/jpeg", JPEG),
+        ]
+        .concat();
+        let segment = [
+            ebml(0x1549_a966, &[0; 8]),
+            ebml(EBML_ATTACHMENTS, &attachments),
+        ]
+        .concat();
+        assert_eq!(data(&matroska_file(&segment)).as_deref(), Some(JPEG));
+    }
+
+    #[test]
+    fn matroska_seek_head() {
+        let attachments = ebml(
+            EBML_ATTACHMENTS,
+            &attachment(b"cover.png", b"image/png", PNG),
+        );
+        let cluster = ebml(EBML_CLUSTER, &[0; 32]);
+        let seek_head = |position: u64| {
+            let seek = [
+                ebml(EBML_SEEK_ID, &EBML_ATTACHMENTS.to_be_bytes()),
+                ebml(EBML_SEEK_POSITION, &position.to_be_bytes()),
+            ]
+            .concat();
+            ebml(EBML_SEEK_HEAD, &ebml(EBML_SEEK, &seek))
+        };
+        let position = (seek_head(0).len() + cluster.len()) as u64;
+        let segment = [seek_head(position), cluster, attachments].concat();
+        assert_eq!(data(&matroska_file(&segment)).as_deref(), Some(PNG));
+    }
+
+    #[test]
+    fn matroska_seek_position_overflow() {
+        let seek_head = |position: &[u8]| {
+            let seek = [
+                ebml(EBML_SEEK_ID, &EBML_ATTACHMENTS.to_be_bytes()),
+                ebml(EBML_SEEK_POSITION, position),
+            ]
+            .concat();
+            ebml(EBML_SEEK_HEAD, &ebml(EBML_SEEK, &seek))
+        };
+        let cluster = ebml(EBML_CLUSTER, &[0; 32]);
+        for position in [&[0xff; 8][..], &[0x01; 9]] {
+            let segment = [seek_head(position), cluster.clone()].concat();
+            assert_eq!(data(&matroska_file(&segment)), None);
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(data(b"RIFF\0\0\0\0WAVEfmt "), None);
+    }
+}
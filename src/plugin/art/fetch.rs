@@ -0,0 +1,50 @@
+use super::extract::Picture;
+use crate::plugin::options::Options;
+use compact_str::CompactString;
+use smol::process::Command;
+use std::process::Stdio;
+use url::Url;
+
+/// Downloads remote art with a user-supplied command, e.g. `curl -fsL`, which gets the URL as its
+/// last argument and writes the image to stdout.
+#[derive(Clone, Debug)]
+pub(super) struct Fetcher {
+    program: CompactString,
+    args: Vec<CompactString>,
+}
+
+impl Fetcher {
+    pub(super) fn new(options: &Options) -> Option<Self> {
+        let mut words = options
+            .art_fetch
+            .split_whitespace()
+            .map(CompactString::from);
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+        })
+    }
+
+    pub(super) async fn fetch(&self, url: &Url) -> anyhow::Result<Picture> {
+        let output = Command::new(self.program.as_str())
+            .args(self.args.iter().map(CompactString::as_str))
+            .arg(url.as_str())
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stderr = stderr.lines().last().unwrap_or_default();
+            anyhow::bail!(
+                "{} failed with {} for {url}: {stderr}",
+                self.program,
+                output.status
+            );
+        }
+        match Picture::new(output.stdout) {
+            Some(picture) => Ok(picture),
+            None => anyhow::bail!("Not an image: {url}"),
+        }
+    }
+}
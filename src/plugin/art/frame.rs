@@ -0,0 +1,117 @@
+use super::{File, normalize::Normalize, publish::Publisher, thumbnail::Thumbnails};
+use crate::{
+    mpv,
+    mpv::Mpv,
+    plugin::{clock::Playing, options::Options},
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tempfile::NamedTempFile;
+
+/// Frames grabbed from the video with `screenshot-to-file`, for video files without any art.
+#[derive(Clone, Debug)]
+pub(super) struct Frame {
+    offset: f64,
+    size: u32,
+    interval: Option<Duration>,
+}
+
+impl Frame {
+    pub(super) fn new(options: &Options) -> Option<Self> {
+        if !options.art_frame {
+            return None;
+        }
+        Some(Self {
+            offset: options.art_frame_offset.max(0.0),
+            size: options.art_frame_size.max(1),
+            interval: (options.art_frame_interval > 0)
+                .then(|| Duration::from_secs(options.art_frame_interval)),
+        })
+    }
+}
+
+/// Fails only if the first frame can't be taken, later ones are refreshes. The first frame is
+/// also stored as the thumbnail of the file given with `thumbnail`.
+pub(super) async fn worker(
+    tx: &Publisher,
+    mpv: &Mpv,
+    playing: &Playing,
+    frame: Frame,
+    normalize: &Normalize,
+    thumbnail: Option<(Thumbnails, PathBuf)>,
+) -> anyhow::Result<()> {
+    wait(mpv, playing, frame.offset).await;
+    let file = screenshot(mpv, &tx.dir, frame.size, normalize, thumbnail).await?;
+    tx.send(File::Temp(file)).await?;
+    let Some(interval) = frame.interval else {
+        return Ok(());
+    };
+    loop {
+        smol::Timer::after(interval).await;
+        let result = match screenshot(mpv, &tx.dir, frame.size, normalize, None).await {
+            Ok(file) => tx.send(File::Temp(file)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::error!(error = %e, "Failed to refresh video frame");
+        }
+    }
+}
+
+/// Waits until playback reaches `offset`, or half the duration for shorter files. The first
+/// frames are often black, and `playback-time` is unavailable until playback has started. Nothing
+/// is polled while paused.
+async fn wait(mpv: &Mpv, playing: &Playing, offset: f64) {
+    loop {
+        playing.wait().await;
+        let time = mpv
+            .get_property::<Option<mpv::Seconds>>("playback-time")
+            .await;
+        let duration = mpv.get_property::<Option<mpv::Seconds>>("duration").await;
+        if let Ok(Some(time)) = time {
+            let duration = duration.ok().flatten().map(f64::from);
+            let offset = match duration {
+                Some(duration) if duration > 0.0 => offset.min(duration / 2.0),
+                _ => offset,
+            };
+            if f64::from(time) >= offset {
+                return;
+            }
+        }
+        smol::Timer::after(Duration::from_secs(1)).await;
+    }
+}
+
+async fn screenshot(
+    mpv: &Mpv,
+    dir: &Path,
+    size: u32,
+    normalize: &Normalize,
+    thumbnail: Option<(Thumbnails, PathBuf)>,
+) -> anyhow::Result<NamedTempFile> {
+    // mpv picks the format from the suffix
+    let screenshot = tempfile::Builder::new()
+        .prefix("frame-")
+        .suffix(".png")
+        .tempfile_in(dir)?;
+    let cmd = mpv::NamedCommand::ScreenshotToFile {
+        filename: screenshot.path().to_owned(),
+        flags: Some(mpv::ScreenshotFlags::Video),
+    };
+    mpv.run_command::<()>(cmd).await?;
+    let (dir, normalize) = (dir.to_owned(), normalize.clone());
+    smol::unblock(move || -> anyhow::Result<_> {
+        let image = image::open(screenshot.path())?;
+        if let Some((thumbnails, path)) = thumbnail
+            && thumbnails.find(&path).is_none()
+            && let Err(e) = thumbnails.store(&path, &image)
+        {
+            tracing::warn!(error = %e, path = %path.display(), "Failed to store thumbnail");
+        }
+        let picture = normalize.encode(image, size)?;
+        Ok(super::write(&picture, &dir)?)
+    })
+    .await
+}
@@ -0,0 +1,145 @@
+use super::extract::Picture;
+use smol::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use std::{
+    cell::RefCell,
+    io,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the current art on `127.0.0.1`, for shells that can't read local files.
+#[derive(Clone, Debug)]
+pub(super) struct Server {
+    port: u16,
+    current: Rc<RefCell<Current>>,
+}
+
+#[derive(Debug, Default)]
+struct Current {
+    serial: u64,
+    path: Option<PathBuf>,
+}
+
+impl Server {
+    pub(super) fn new(ex: &smol::LocalExecutor, port: u16) -> io::Result<(Self, smol::Task<()>)> {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let port = listener.local_addr()?.port();
+        let listener = TcpListener::try_from(listener)?;
+        let this = Self {
+            port,
+            current: Rc::default(),
+        };
+        let task = ex.spawn(this.clone().run(listener));
+        Ok((this, task))
+    }
+
+    /// Makes `path` the current art. Every file gets a new URL, so shells don't show stale art.
+    pub(super) fn serve(&self, path: &Path) -> Url {
+        let mut current = self.current.borrow_mut();
+        current.serial += 1;
+        current.path = Some(path.to_owned());
+        let suffix = path.extension().map(|ext| ext.to_string_lossy());
+        let url = format!(
+            "http://127.0.0.1:{}/{}.{}",
+            self.port,
+            current.serial,
+            suffix.as_deref().unwrap_or("jpg"),
+        );
+        Url::parse(&url).expect("valid url")
+    }
+
+    async fn run(self, listener: TcpListener) {
+        // connections are handled concurrently, so a stuck client doesn't block the others. They
+        // own all they need, so they run on an executor of their own, which goes with this task.
+        let connections = smol::LocalExecutor::new();
+        connections
+            .run(async {
+                loop {
+                    let stream = match listener.accept().await {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            tracing::error!(error = %e, "Failed to accept art HTTP connection");
+                            continue;
+                        }
+                    };
+                    connections.spawn(self.clone().connection(stream)).detach();
+                }
+            })
+            .await
+    }
+
+    async fn connection(self, stream: TcpStream) {
+        let timeout = async {
+            smol::Timer::after(TIMEOUT).await;
+            Err::<(), _>(io::ErrorKind::TimedOut.into())
+        };
+        if let Err(e) = smol::future::or(self.handle(stream), timeout).await {
+            tracing::debug!(error = %e, "Failed to serve art over HTTP");
+        }
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut head = Vec::new();
+        let mut buf = [0; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            let len = stream.read(&mut buf).await?;
+            if len == 0 || head.len() > 8192 {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            head.extend_from_slice(&buf[..len]);
+        }
+        let head = String::from_utf8_lossy(&head);
+        let Some(target) = head
+            .strip_prefix("GET ")
+            .and_then(|rest| rest.split(' ').next())
+        else {
+            return respond(&mut stream, "405 Method Not Allowed", None).await;
+        };
+        let path = {
+            let current = self.current.borrow();
+            let serial = target
+                .strip_prefix('/')
+                .and_then(|target| target.split_once('.'))
+                .and_then(|(serial, _)| serial.parse::<u64>().ok());
+            current
+                .path
+                .clone()
+                .filter(|_| serial == Some(current.serial))
+        };
+        let Some(path) = path else {
+            return respond(&mut stream, "404 Not Found", None).await;
+        };
+        let Some(picture) = Picture::new(smol::fs::read(&path).await?) else {
+            return respond(&mut stream, "404 Not Found", None).await;
+        };
+        respond(&mut stream, "200 OK", Some(&picture)).await
+    }
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    picture: Option<&Picture>,
+) -> io::Result<()> {
+    let mut response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+    match picture {
+        Some(picture) => {
+            response.push_str(&format!("Content-Type: {}\r\n", picture.mime));
+            response.push_str(&format!("Content-Length: {}\r\n\r\n", picture.data.len()));
+        }
+        None => response.push_str("Content-Length: 0\r\n\r\n"),
+    }
+    stream.write_all(response.as_bytes()).await?;
+    if let Some(picture) = picture {
+        stream.write_all(&picture.data).await?;
+    }
+    stream.flush().await
+}
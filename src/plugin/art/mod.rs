@@ -0,0 +1,684 @@
+pub(super) use self::publish::Art;
+use self::{
+    cache::Cache,
+    fetch::Fetcher,
+    frame::Frame,
+    normalize::Normalize,
+    publish::{Dir, Publisher},
+    sidecar::Sidecar,
+    thumbnail::Thumbnails,
+};
+use super::{clock::Playing, options::Options};
+use crate::mpv::{self, Mpv};
+use anyhow::Context;
+use compact_str::format_compact;
+use smol::{lock::Semaphore, process::Command};
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Stdio,
+    rc::Rc,
+    time::Duration,
+};
+use tempfile::NamedTempFile;
+use url::Url;
+
+mod cache;
+mod extract;
+mod fetch;
+mod frame;
+mod http;
+pub(super) mod normalize;
+pub(super) mod publish;
+pub(super) mod sidecar;
+mod thumbnail;
+
+pub(super) enum File {
+    Temp(NamedTempFile),
+    Cached(PathBuf),
+    /// An image of the user's, e.g. `cover.jpg`.
+    External(PathBuf),
+}
+
+impl File {
+    pub(super) fn path(&self) -> &Path {
+        match self {
+            Self::Temp(file) => file.path(),
+            Self::Cached(path) | Self::External(path) => path,
+        }
+    }
+}
+
+pub(super) struct State {
+    task: Option<smol::Task<()>>,
+    /// The candidates the current task was started with.
+    tracks: Vec<Track>,
+    prefetch: Option<smol::Task<()>>,
+    file: Option<File>,
+    worker: Worker,
+    _http: Option<smol::Task<()>>,
+    // dropped last, it removes the files above
+    _dir: Option<Dir>,
+}
+
+impl State {
+    pub(super) fn new(
+        ex: &smol::LocalExecutor,
+        options: &Options,
+        playing: Playing,
+    ) -> (Self, kanal::AsyncReceiver<Art>) {
+        let (tx, rx) = kanal::bounded_async(0);
+        let cache = Cache::new(options);
+        if let Some(cache) = cache.clone() {
+            evict(cache);
+        }
+        let dir = Dir::new(options)
+            .inspect_err(|e| tracing::error!(error = %e, "Failed to create art directory"))
+            .ok();
+        let (http, http_task) = match options.art_url {
+            publish::UrlMode::Http => match http::Server::new(ex, options.art_http_port) {
+                Ok((http, task)) => (Some(http), Some(task)),
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to start art HTTP server");
+                    (None, None)
+                }
+            },
+            _ => (None, None),
+        };
+        let publisher = Publisher {
+            tx,
+            dir: dir
+                .as_ref()
+                .map_or_else(env::temp_dir, |dir| dir.path().to_owned()),
+            mode: options.art_url,
+            http,
+        };
+        let worker = Worker {
+            tx: publisher,
+            cache,
+            fetcher: Fetcher::new(options),
+            frame: Frame::new(options),
+            sidecar: Sidecar::new(options),
+            thumbnails: Thumbnails::new(options),
+            normalize: Normalize::new(options),
+            prefetched: Rc::default(),
+            playing,
+        };
+        let this = Self {
+            task: None,
+            tracks: Vec::new(),
+            prefetch: None,
+            file: None,
+            worker,
+            _http: http_task,
+            _dir: dir,
+        };
+        (this, rx)
+    }
+
+    pub(super) fn clear(&mut self) {
+        drop(self.task.take());
+        self.tracks.clear();
+        drop(self.file.take());
+    }
+
+    /// Tries `tracks` in order until one of them yields an image, unless they are the same as
+    /// before, e.g. when only a subtitle track was added. Returns whether art is on its way.
+    pub(super) fn spawn_worker(
+        &mut self,
+        ex: &smol::LocalExecutor,
+        tracks: Vec<Track>,
+        mpv: &Mpv,
+    ) -> bool {
+        if tracks == self.tracks {
+            return self.task.as_ref().is_some_and(|task| !task.is_finished());
+        }
+        self.tracks.clone_from(&tracks);
+        let tracks = tracks.into_iter().filter(|track| match track {
+            Track::Sidecar(_) => self.worker.sidecar.is_some(),
+            Track::Thumbnail(_) => self.worker.thumbnails.is_some(),
+            Track::Frame(_) => self.worker.frame.is_some(),
+            _ => true,
+        });
+        let tracks: Vec<_> = tracks.collect();
+        if tracks.is_empty() {
+            self.task = None;
+            return false;
+        }
+        self.task = Some(ex.spawn(self.worker.clone().run(tracks, mpv.clone())));
+        true
+    }
+
+    /// Extracts the art of playlist entry `index` in the background.
+    pub(super) fn prefetch(&mut self, ex: &smol::LocalExecutor, index: u64, mpv: &Mpv) {
+        let task = self.worker.clone().prefetch(mpv.clone(), index);
+        self.prefetch = Some(ex.spawn(task));
+    }
+
+    /// Keeps the file of the art that is currently shown alive.
+    pub(super) fn set_art(&mut self, art: Art) -> Url {
+        self.file = art.file;
+        art.url
+    }
+}
+
+/// `$XDG_CACHE_HOME`, or `~/.cache` without it.
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(Path::new(&env::var_os("HOME")?).join(".cache")))
+}
+
+fn evict(cache: Cache) {
+    smol::unblock(move || {
+        if let Err(e) = cache.evict() {
+            tracing::error!(error = %e, "Failed to evict art cache");
+        }
+    })
+    .detach();
+}
+
+/// Limits the number of concurrent ffmpeg processes when skipping through tracks quickly.
+static FFMPEG: Semaphore = Semaphore::new(2);
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Gives up on `future` after [`TIMEOUT`].
+async fn timeout<T>(future: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    let timeout = async {
+        smol::Timer::after(TIMEOUT).await;
+        Err(anyhow::anyhow!("Timed out after {TIMEOUT:?}"))
+    };
+    smol::future::or(future, timeout).await
+}
+
+/// Art of the next playlist entry, extracted while the current one plays.
+struct Prefetched {
+    path: PathBuf,
+    /// Whether it came from the file itself or is a sidecar.
+    embedded: bool,
+    file: File,
+}
+
+#[derive(Clone)]
+struct Worker {
+    tx: Publisher,
+    cache: Option<Cache>,
+    fetcher: Option<Fetcher>,
+    frame: Option<Frame>,
+    sidecar: Option<Sidecar>,
+    thumbnails: Option<Thumbnails>,
+    normalize: Normalize,
+    prefetched: Rc<RefCell<Option<Prefetched>>>,
+    playing: Playing,
+}
+
+impl Worker {
+    async fn run(self, tracks: Vec<Track>, mpv: Mpv) {
+        for track in tracks {
+            if let Some(file) = self.take_prefetched(&track) {
+                match self.tx.send(file).await {
+                    Ok(()) => return,
+                    Err(e) => {
+                        tracing::warn!(error = %e, ?track, "Failed to publish prefetched art")
+                    }
+                }
+            }
+            let result = match &track {
+                Track::Frame(_) => self.publish(&track, &mpv).await,
+                _ => timeout(self.publish(&track, &mpv)).await,
+            };
+            match result {
+                Ok(()) => return,
+                Err(e) => tracing::warn!(error = %e, ?track, "Failed to get art"),
+            }
+        }
+    }
+
+    async fn publish(&self, track: &Track, mpv: &Mpv) -> anyhow::Result<()> {
+        let file = match track {
+            Track::Embedded(path, index) => self.embedded(path, *index).await?,
+            Track::External(path) => self.external(path.clone()).await?,
+            Track::Sidecar(path) => self.sidecar(path.clone()).await?,
+            Track::Remote(url) => {
+                let Some(fetcher) = &self.fetcher else {
+                    return self.tx.send_url(url.clone()).await;
+                };
+                match self.remote(fetcher, url).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        tracing::warn!(error = %e, %url, "Failed to fetch art, passing the URL on");
+                        return self.tx.send_url(url.clone()).await;
+                    }
+                }
+            }
+            Track::Thumbnail(path) => {
+                let (thumbnails, path) = (self.thumbnails.clone(), path.clone());
+                let found = smol::unblock(move || thumbnails?.find(&path)).await;
+                self.external(found.context("No thumbnail found")?).await?
+            }
+            Track::Frame(path) => {
+                let frame = self.frame.clone().context("Video frames are disabled")?;
+                let thumbnail = match (&self.thumbnails, path) {
+                    (Some(thumbnails), mpv::Path::Path(path)) if thumbnails.generate => {
+                        Some((thumbnails.clone(), path.clone()))
+                    }
+                    _ => None,
+                };
+                let (tx, normalize) = (&self.tx, &self.normalize);
+                return frame::worker(tx, mpv, &self.playing, frame, normalize, thumbnail).await;
+            }
+        };
+        self.tx.send(file).await
+    }
+
+    fn take_prefetched(&self, track: &Track) -> Option<File> {
+        let (path, embedded) = match track {
+            Track::Embedded(path, _) => (path, true),
+            Track::Sidecar(path) => (path, false),
+            _ => return None,
+        };
+        let mut prefetched = self.prefetched.borrow_mut();
+        prefetched
+            .take_if(|prefetched| prefetched.path == *path && prefetched.embedded == embedded)
+            .map(|prefetched| prefetched.file)
+    }
+
+    /// Extracts the art of playlist entry `index`, for [`Self::run`] to pick up once it starts.
+    /// The track list isn't known yet, so only embedded art and sidecars are tried. Embedded art
+    /// is only read natively, ffmpeg can't tell covers from the frames of videos.
+    async fn prefetch(self, mpv: Mpv, index: u64) {
+        if let Err(e) = timeout(self.try_prefetch(&mpv, index)).await {
+            tracing::debug!(error = %e, index, "Failed to prefetch art");
+        }
+    }
+
+    async fn try_prefetch(&self, mpv: &Mpv, index: u64) -> anyhow::Result<()> {
+        let playlist: Vec<mpv::PlaylistEntry> = mpv.get_property("playlist").await?;
+        let entry = usize::try_from(index)
+            .ok()
+            .and_then(|index| playlist.get(index));
+        let entry = entry.context("No such playlist entry")?;
+        let path = match Url::parse(&entry.filename) {
+            Ok(url) if url.scheme() == "file" => url
+                .to_file_path()
+                .map_err(|()| anyhow::anyhow!("Invalid file URL"))?,
+            Ok(_) => anyhow::bail!("Not a local file"),
+            Err(_) => {
+                let working_directory: PathBuf = mpv.get_property("working-directory").await?;
+                working_directory.join(&entry.filename)
+            }
+        };
+        if self
+            .prefetched
+            .borrow()
+            .as_ref()
+            .is_some_and(|prefetched| prefetched.path == path)
+        {
+            return Ok(());
+        }
+        let picture = native(&path).await;
+        let (embedded, file) = match picture.context("No embedded art") {
+            // stored aside from the cache, the stream isn't known to key it by
+            Ok(picture) => (true, self.store(picture, None).await?),
+            Err(e) => {
+                tracing::debug!(error = %e, path = %path.display(), "No embedded art to prefetch");
+                (false, self.sidecar(path.clone()).await?)
+            }
+        };
+        *self.prefetched.borrow_mut() = Some(Prefetched {
+            path,
+            embedded,
+            file,
+        });
+        Ok(())
+    }
+
+    /// `index` is the stream of the art.
+    async fn embedded(&self, path: &Path, index: u64) -> anyhow::Result<File> {
+        let key = match &self.cache {
+            Some(_) => {
+                let (path, normalize) = (path.to_owned(), self.normalize.key());
+                Some(smol::unblock(move || Cache::key(&path, index, &normalize)).await?)
+            }
+            None => None,
+        };
+        if let Some(cached) = self.cached(key.clone()).await {
+            return Ok(cached);
+        }
+        let picture = match native(path).await {
+            Some(picture) => picture,
+            None => ffmpeg(path, index).await?,
+        };
+        self.store(picture, key).await
+    }
+
+    async fn remote(&self, fetcher: &Fetcher, url: &Url) -> anyhow::Result<File> {
+        let key = Cache::remote_key(url, &self.normalize.key());
+        if let Some(cached) = self.cached(Some(key.clone())).await {
+            return Ok(cached);
+        }
+        let picture = fetcher.fetch(url).await?;
+        self.store(picture, Some(key)).await
+    }
+
+    async fn cached(&self, key: Option<String>) -> Option<File> {
+        let (cache, key) = (self.cache.clone()?, key?);
+        let cached = smol::unblock(move || cache.get(&key)).await?;
+        Some(File::Cached(cached))
+    }
+
+    /// Normalizes `picture` into the cache, or into a temporary file without one.
+    async fn store(&self, picture: extract::Picture, key: Option<String>) -> anyhow::Result<File> {
+        let cache = self.cache.clone().zip(key);
+        let (dir, normalize) = (self.tx.dir.clone(), self.normalize.clone());
+        let (file, cache) = smol::unblock(move || -> anyhow::Result<_> {
+            let picture = normalize.apply(picture)?;
+            let Some((cache, key)) = cache else {
+                return Ok((File::Temp(write(&picture, &dir)?), None));
+            };
+            let mut file = cache.tempfile()?;
+            file.write_all(&picture.data)?;
+            let cached = cache.insert(&key, picture.suffix(), file)?;
+            Ok((File::Cached(cached), Some(cache)))
+        })
+        .await?;
+        if let Some(cache) = cache {
+            evict(cache);
+        }
+        Ok(file)
+    }
+
+    async fn sidecar(&self, path: PathBuf) -> anyhow::Result<File> {
+        let sidecar = self.sidecar.clone();
+        let found = smol::unblock(move || sidecar?.search(&path)).await;
+        self.external(found.context("No sidecar art found")?).await
+    }
+
+    /// Uses an existing image, converting it only if it isn't compatible.
+    async fn external(&self, path: PathBuf) -> anyhow::Result<File> {
+        let (dir, normalize) = (self.tx.dir.clone(), self.normalize.clone());
+        smol::unblock(move || {
+            let Some(picture) = extract::Picture::new(fs::read(&path)?) else {
+                anyhow::bail!("Not an image: {}", path.display());
+            };
+            Ok(if normalize.is_compatible(&picture) {
+                File::External(path)
+            } else {
+                File::Temp(write(&normalize.apply(picture)?, &dir)?)
+            })
+        })
+        .await
+    }
+}
+
+/// Writes `picture` to a temporary file with the suffix matching its format.
+fn write(picture: &extract::Picture, dir: &Path) -> io::Result<NamedTempFile> {
+    let mut file = publish::tempfile(dir, picture.suffix())?;
+    file.write_all(&picture.data)?;
+    Ok(file)
+}
+
+/// Reads the cover straight out of the container, which is much cheaper than spawning ffmpeg.
+async fn native(path: &Path) -> Option<extract::Picture> {
+    let path = path.to_owned();
+    match smol::unblock(move || extract::extract(&path)).await {
+        Ok(picture) => picture,
+        Err(e) => {
+            tracing::debug!(error = %e, "Failed to extract art natively, falling back to ffmpeg");
+            None
+        }
+    }
+}
+
+async fn ffmpeg(path: &Path, index: u64) -> anyhow::Result<extract::Picture> {
+    let _permit = FFMPEG.acquire().await;
+    let output = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(path)
+        .arg("-map")
+        .arg(format_compact!("0:{index}"))
+        .args(["-frames:v", "1", "-c:v", "png", "-f", "image2pipe", "-"])
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.lines().last().unwrap_or_default();
+        anyhow::bail!(
+            "ffmpeg failed with {} for {}: {stderr}",
+            output.status,
+            path.display()
+        );
+    }
+    extract::Picture::new(output.stdout)
+        .with_context(|| format!("No art extracted from {}", path.display()))
+}
+
+/// Art candidates, best first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum Track {
+    Embedded(PathBuf, u64),
+    External(PathBuf),
+    /// An `http(s)` URL, e.g. from `--cover-art-files` or the thumbnail of a ytdl stream.
+    Remote(Url),
+    /// Images next to a local file, searched for by the worker.
+    Sidecar(PathBuf),
+    /// A thumbnail of a local video file from the thumbnail spec cache.
+    Thumbnail(PathBuf),
+    /// A frame of the video, the path is only there to tell files apart and to store thumbnails.
+    Frame(mpv::Path),
+}
+
+pub(super) fn find(
+    track_list: &[mpv::Track],
+    path: &Option<mpv::Path>,
+    working_directory: &Option<PathBuf>,
+    thumbnail: Option<Url>,
+) -> Vec<Track> {
+    let any_path = path;
+    let path = path.as_ref().and_then(|x| match x {
+        mpv::Path::Path(path) => Some(path),
+        _ => None,
+    });
+    let absolute = |file: &PathBuf| {
+        let path = working_directory.as_ref().map(|dir| dir.join(file));
+        path.unwrap_or_else(|| file.clone())
+    };
+    let external = |file: &PathBuf| {
+        let url = file.to_str().and_then(|file| Url::parse(file).ok());
+        match url {
+            Some(url) if matches!(url.scheme(), "http" | "https") => Track::Remote(url),
+            _ => Track::External(absolute(file)),
+        }
+    };
+    let (mut album_art, mut images, mut embedded) = (Vec::new(), Vec::new(), Vec::new());
+    let mut video = false;
+    for track in track_list {
+        match track {
+            mpv::Track::ExternalAlbumArt {
+                external_filename, ..
+            } => {
+                album_art.push(external(external_filename));
+            }
+            mpv::Track::ExternalImage {
+                external_filename, ..
+            } => {
+                images.push(external(external_filename));
+            }
+            &mpv::Track::EmbeddedAlbumArt { ff_index, .. } => {
+                if let Some(path) = path {
+                    embedded.insert(0, Track::Embedded(absolute(path), ff_index));
+                }
+            }
+            &mpv::Track::EmbeddedImage { ff_index, .. } => {
+                if let Some(path) = path {
+                    if track_list.len() == 1 {
+                        images.push(Track::External(absolute(path)));
+                    } else {
+                        embedded.push(Track::Embedded(absolute(path), ff_index));
+                    }
+                }
+            }
+            mpv::Track::Video { .. } => video = true,
+            mpv::Track::Other {} => (),
+        }
+    }
+    // mpv's own album art comes first, the last one being the one it would show
+    album_art.reverse();
+    let mut tracks = album_art;
+    tracks.append(&mut images);
+    tracks.append(&mut embedded);
+    if let Some(path) = path {
+        tracks.push(Track::Sidecar(absolute(path)));
+    }
+    if video && let Some(path) = path {
+        tracks.push(Track::Thumbnail(absolute(path)));
+    }
+    tracks.extend(thumbnail.map(Track::Remote));
+    if video && let Some(any_path) = any_path {
+        let any_path = match (any_path, path) {
+            (mpv::Path::Path(_), Some(path)) => mpv::Path::Path(absolute(path)),
+            (any_path, _) => any_path.clone(),
+        };
+        tracks.push(Track::Frame(any_path));
+    }
+    tracks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compact_str::CompactString;
+    use image::{DynamicImage, codecs::png::PngEncoder};
+    use smol::{LocalExecutor, net::unix::UnixStream};
+
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::new_rgb8(4, 4)
+            .write_with_encoder(PngEncoder::new(&mut data))
+            .unwrap();
+        data
+    }
+
+    /// A fetch command that serves `image` for `cover.png` and fails for everything else, logging
+    /// the URLs it gets to `requests`.
+    fn fetcher(dir: &Path, image: &[u8]) -> CompactString {
+        fs::write(dir.join("cover.png"), image).unwrap();
+        let script = format!(
+            "#!/bin/sh\n\
+             echo \"$1\" >> '{dir}/requests'\n\
+             case \"$1\" in */cover.png) exec cat '{dir}/cover.png' ;; esac\n\
+             exit 22\n",
+            dir = dir.display(),
+        );
+        let path = dir.join("fetch");
+        fs::write(&path, script).unwrap();
+        format_compact!("sh {}", path.display())
+    }
+
+    fn requests(dir: &Path) -> usize {
+        fs::read_to_string(dir.join("requests")).map_or(0, |s| s.lines().count())
+    }
+
+    fn worker(options: &Options, dir: &Path) -> (Worker, kanal::AsyncReceiver<Art>) {
+        let (tx, rx) = kanal::bounded_async(0);
+        let worker = Worker {
+            tx: Publisher {
+                tx,
+                dir: dir.join("published"),
+                mode: publish::UrlMode::File,
+                http: None,
+            },
+            cache: Some(Cache::in_dir(dir.join("cache"), options)),
+            fetcher: Fetcher::new(options),
+            frame: None,
+            sidecar: None,
+            thumbnails: None,
+            normalize: Normalize::new(options),
+            prefetched: Rc::default(),
+            playing: Playing::default(),
+        };
+        fs::create_dir(&worker.tx.dir).unwrap();
+        (worker, rx)
+    }
+
+    /// Publishes `url` and returns what the main loop would get.
+    async fn publish(worker: &Worker, rx: &kanal::AsyncReceiver<Art>, mpv: &Mpv, url: &Url) -> Art {
+        let track = Track::Remote(url.clone());
+        let (result, art) = smol::future::zip(worker.publish(&track, mpv), rx.recv()).await;
+        result.unwrap();
+        art.unwrap()
+    }
+
+    #[test]
+    fn fetch() {
+        let ex = LocalExecutor::new();
+        let dir = tempfile::tempdir().unwrap();
+        let options = Options {
+            art_fetch: fetcher(dir.path(), &png()),
+            ..Default::default()
+        };
+        let (worker, rx) = worker(&options, dir.path());
+        let (stream, _other) = UnixStream::pair().unwrap();
+        let (mpv, _) = Mpv::new(&ex, stream);
+        smol::block_on(ex.run(async {
+            // downloaded and cached, then served from the cache
+            let url = Url::parse("https://example.com/cover.png").unwrap();
+            for _ in 0..2 {
+                let art = publish(&worker, &rx, &mpv, &url).await;
+                assert!(art.file.is_some());
+                assert_eq!(art.url.scheme(), "file");
+                let path = art.url.to_file_path().unwrap();
+                assert!(path.starts_with(&worker.tx.dir));
+                assert_eq!(fs::read(path).unwrap(), png());
+            }
+            assert_eq!(requests(dir.path()), 1);
+            let key = Cache::remote_key(&url, &worker.normalize.key());
+            let cached = worker.cache.as_ref().unwrap().get(&key).unwrap();
+            assert_eq!(cached.extension().unwrap(), "png");
+
+            // a failed download passes the URL on
+            let url = Url::parse("https://example.com/missing.png").unwrap();
+            let art = publish(&worker, &rx, &mpv, &url).await;
+            assert!(art.file.is_none());
+            assert_eq!(art.url, url);
+            assert_eq!(requests(dir.path()), 2);
+        }));
+    }
+
+    #[test]
+    fn without_fetcher() {
+        let ex = LocalExecutor::new();
+        let dir = tempfile::tempdir().unwrap();
+        let (worker, rx) = worker(&Options::default(), dir.path());
+        let (stream, _other) = UnixStream::pair().unwrap();
+        let (mpv, _) = Mpv::new(&ex, stream);
+        let url = Url::parse("https://example.com/cover.jpg").unwrap();
+        let art = smol::block_on(ex.run(publish(&worker, &rx, &mpv, &url)));
+        assert!(art.file.is_none());
+        assert_eq!(art.url, url);
+    }
+
+    #[test]
+    fn stream_prefers_ytdl_thumbnail_over_frame() {
+        let track_list: Vec<mpv::Track> = serde_json::from_str(
+            r#"[
+                {"type": "video", "image": false, "albumart": false},
+                {"type": "audio", "lang": "en"}
+            ]"#,
+        )
+        .unwrap();
+        let url = Url::parse("https://www.youtube.com/watch?v=dQw4w9WgXcQ").unwrap();
+        let path = Some(mpv::Path::Url(url.clone()));
+        let thumbnail = Url::parse("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg").unwrap();
+        let tracks = find(&track_list, &path, &None, Some(thumbnail.clone()));
+        let frame = Track::Frame(mpv::Path::Url(url));
+        assert_eq!(tracks, [Track::Remote(thumbnail), frame.clone()]);
+        assert_eq!(find(&track_list, &path, &None, None), [frame]);
+    }
+}
@@ -0,0 +1,104 @@
+use super::extract::Picture;
+use crate::plugin::options::Options;
+use image::{
+    DynamicImage, ImageError, ImageReader,
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+};
+use std::io::Cursor;
+use strum::EnumString;
+
+#[derive(Clone, Copy, Debug, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub(crate) enum Format {
+    #[strum(serialize = "jpeg", serialize = "jpg")]
+    Jpeg,
+    Png,
+}
+
+impl Format {
+    const fn mime(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+/// Shells don't agree on which formats they can show, and some choke on huge scans.
+#[derive(Clone, Debug)]
+pub(super) struct Normalize {
+    size: u32,
+    format: Format,
+    quality: u8,
+}
+
+impl Normalize {
+    pub(super) fn new(options: &Options) -> Self {
+        Self {
+            size: options.art_size,
+            format: options.art_format,
+            quality: options.art_quality.clamp(1, 100),
+        }
+    }
+
+    /// Part of the cache key, so that changing the options doesn't serve stale entries.
+    pub(super) fn key(&self) -> String {
+        format!("{}\0{:?}\0{}", self.size, self.format, self.quality)
+    }
+
+    /// JPEG and PNG within the maximum size are published as is.
+    pub(super) fn is_compatible(&self, picture: &Picture) -> bool {
+        if !matches!(picture.mime, "image/jpeg" | "image/png") {
+            return false;
+        }
+        self.size == 0
+            || ImageReader::new(Cursor::new(&picture.data))
+                .with_guessed_format()
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok())
+                .is_some_and(|(width, height)| width <= self.size && height <= self.size)
+    }
+
+    /// Returns `picture` untouched if it is compatible. Formats that can't be decoded, e.g. AVIF,
+    /// are an error, so that the next candidate is tried instead of art shells can't show.
+    pub(super) fn apply(&self, picture: Picture) -> anyhow::Result<Picture> {
+        if self.is_compatible(&picture) {
+            return Ok(picture);
+        }
+        match image::load_from_memory(&picture.data) {
+            Ok(image) => self.encode(image, self.size),
+            Err(ImageError::Unsupported(e)) => anyhow::bail!("Can't convert {}: {e}", picture.mime),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Scales `image` down to fit in `size`, unless it is `0`, and encodes it.
+    pub(super) fn encode(&self, image: DynamicImage, size: u32) -> anyhow::Result<Picture> {
+        let image = if size > 0 && (image.width() > size || image.height() > size) {
+            image.thumbnail(size, size)
+        } else {
+            image
+        };
+        let mut data = Vec::new();
+        match self.format {
+            Format::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut data, self.quality);
+                image.to_rgb8().write_with_encoder(encoder)?;
+            }
+            Format::Png => image
+                .to_rgba8()
+                .write_with_encoder(PngEncoder::new(&mut data))?,
+        }
+        tracing::debug!(
+            mime = self.format.mime(),
+            width = image.width(),
+            height = image.height(),
+            len = data.len(),
+            "Normalized art",
+        );
+        Ok(Picture {
+            mime: self.format.mime(),
+            data,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use super::{File, extract::Picture, http};
+use crate::plugin::options::Options;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{
+    env, fs, io,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::{Path, PathBuf},
+    process,
+};
+use strum::EnumString;
+use tempfile::NamedTempFile;
+use url::Url;
+
+#[derive(Clone, Copy, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum UrlMode {
+    /// A `file://` URL under the art directory.
+    File,
+    /// The image itself as a `data:` URI.
+    Data,
+    /// A URL served by a local HTTP server.
+    Http,
+}
+
+pub(super) struct Art {
+    /// `None` for remote art that is published by its URL.
+    pub(super) file: Option<File>,
+    pub(super) url: Url,
+}
+
+/// The directory of this process under the art directory, removed when dropped.
+#[derive(Debug)]
+pub(super) struct Dir(PathBuf);
+
+impl Dir {
+    pub(super) fn new(options: &Options) -> io::Result<Self> {
+        let runtime_dir = env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty());
+        let base = if !options.art_dir.is_empty() {
+            PathBuf::from(options.art_dir.as_str())
+        } else if let Some(runtime_dir) = runtime_dir {
+            PathBuf::from(runtime_dir).join("mpv-mpris2")
+        } else {
+            // getuid always succeeds
+            let uid = unsafe { libc::getuid() };
+            let base = env::temp_dir().join(format!("mpv-mpris2-{uid}"));
+            private_dir(&base, uid)?;
+            base
+        };
+        if let Err(e) = clean(&base) {
+            tracing::warn!(error = %e, "Failed to remove stale art directories");
+        }
+        let dir = base.join(process::id().to_string());
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)?;
+        Ok(Self(dir))
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates `dir` in a shared directory like `/tmp`, where anyone could have created it first, and
+/// checks that it is ours and private.
+fn private_dir(dir: &Path, uid: libc::uid_t) -> io::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+        result => result?,
+    }
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory of ours", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the directories of processes that are gone, e.g. after a crash.
+fn clean(base: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(base) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        entries => entries?,
+    };
+    for entry in entries {
+        let entry = entry?;
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        // signal 0 only checks whether the process exists
+        if unsafe { libc::kill(pid, 0) } < 0
+            && io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+        {
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a temporary file in `dir` with the given suffix.
+pub(super) fn tempfile(dir: &Path, suffix: &str) -> io::Result<NamedTempFile> {
+    tempfile::Builder::new()
+        .prefix("art-")
+        .suffix(suffix)
+        .tempfile_in(dir)
+}
+
+/// Turns files into URLs that shells can load, then sends them to the main loop.
+#[derive(Clone)]
+pub(super) struct Publisher {
+    pub(super) tx: kanal::AsyncSender<Art>,
+    pub(super) dir: PathBuf,
+    pub(super) mode: UrlMode,
+    pub(super) http: Option<http::Server>,
+}
+
+impl Publisher {
+    pub(super) async fn send(&self, file: File) -> anyhow::Result<()> {
+        let art = self.publish(file).await?;
+        self.tx.send(art).await?;
+        Ok(())
+    }
+
+    /// Publishes remote art as is, shells load it themselves.
+    pub(super) async fn send_url(&self, url: Url) -> anyhow::Result<()> {
+        self.tx.send(Art { file: None, url }).await?;
+        Ok(())
+    }
+
+    async fn publish(&self, file: File) -> anyhow::Result<Art> {
+        let (dir, mode) = (self.dir.clone(), self.mode);
+        let (file, url) = smol::unblock(move || -> anyhow::Result<_> {
+            Ok(match mode {
+                UrlMode::File => {
+                    // cached and external files may be somewhere sandboxed shells can't see
+                    let file = match file {
+                        File::Cached(path) | File::External(path) if !path.starts_with(&dir) => {
+                            let suffix = path
+                                .extension()
+                                .map(|ext| format!(".{}", ext.to_string_lossy()));
+                            let temp = tempfile(&dir, suffix.as_deref().unwrap_or_default())?;
+                            fs::copy(&path, temp.path())?;
+                            File::Temp(temp)
+                        }
+                        file => file,
+                    };
+                    let url = Url::from_file_path(file.path())
+                        .map_err(|()| anyhow::anyhow!("Invalid art path"))?;
+                    (file, Some(url))
+                }
+                UrlMode::Data => {
+                    let Some(picture) = Picture::new(fs::read(file.path())?) else {
+                        anyhow::bail!("Unknown art format");
+                    };
+                    let data = STANDARD.encode(&picture.data);
+                    let url = Url::parse(&format!("data:{};base64,{data}", picture.mime))?;
+                    (file, Some(url))
+                }
+                UrlMode::Http => (file, None),
+            })
+        })
+        .await?;
+        let url = match (url, &self.http) {
+            (Some(url), _) => url,
+            (None, Some(http)) => http.serve(file.path()),
+            (None, None) => anyhow::bail!("Art HTTP server is not running"),
+        };
+        Ok(Art {
+            file: Some(file),
+            url,
+        })
+    }
+}
@@ -0,0 +1,181 @@
+use crate::plugin::options::Options;
+use compact_str::CompactString;
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "webp", "gif", "bmp", "avif"];
+
+#[derive(Clone, Debug)]
+enum Token {
+    Literal(CompactString),
+    /// `*`
+    Any,
+    /// `?`
+    One,
+    /// `{basename}`, the name of the playing file without its extension.
+    Basename,
+}
+
+/// A case-insensitive glob like `cover.*` or `{basename}.*`, matched against file names.
+#[derive(Clone, Debug)]
+pub(crate) struct Glob(Vec<Token>);
+
+impl FromStr for Glob {
+    type Err = anyhow::Error;
+
+    fn from_str(mut s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Vec::new();
+        while let Some(c) = s.chars().next() {
+            match c {
+                '*' => tokens.push(Token::Any),
+                '?' => tokens.push(Token::One),
+                '{' => {
+                    let Some(rest) = s.strip_prefix("{basename}") else {
+                        anyhow::bail!("Unknown placeholder in art pattern");
+                    };
+                    tokens.push(Token::Basename);
+                    s = rest;
+                    continue;
+                }
+                _ => {
+                    let end = s.find(['*', '?', '{']).unwrap_or(s.len());
+                    tokens.push(Token::Literal(s[..end].to_lowercase().into()));
+                    s = &s[end..];
+                    continue;
+                }
+            }
+            s = &s[c.len_utf8()..];
+        }
+        Ok(Self(tokens))
+    }
+}
+
+impl Glob {
+    fn matches(&self, name: &str, basename: &str) -> bool {
+        fn match_tokens(tokens: &[Token], s: &str, basename: &str) -> bool {
+            match tokens.split_first() {
+                None => s.is_empty(),
+                Some((Token::Literal(literal), rest)) => s
+                    .strip_prefix(literal.as_str())
+                    .is_some_and(|s| match_tokens(rest, s, basename)),
+                Some((Token::Basename, rest)) => s
+                    .strip_prefix(basename)
+                    .is_some_and(|s| match_tokens(rest, s, basename)),
+                Some((Token::One, rest)) => {
+                    let mut chars = s.chars();
+                    chars.next().is_some() && match_tokens(rest, chars.as_str(), basename)
+                }
+                Some((Token::Any, rest)) => s
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain([s.len()])
+                    .any(|i| match_tokens(rest, &s[i..], basename)),
+            }
+        }
+        match_tokens(&self.0, &name.to_lowercase(), &basename.to_lowercase())
+    }
+}
+
+/// A `|`-separated list of [`Glob`]s, in order of preference.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Globs(Vec<Glob>);
+
+impl FromStr for Globs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let globs = s.split('|').filter(|glob| !glob.is_empty());
+        globs
+            .map(str::parse)
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+}
+
+/// Images next to the playing file, for art that mpv's `cover-art-auto` doesn't pick up.
+#[derive(Clone, Debug)]
+pub(super) struct Sidecar {
+    globs: Globs,
+    parent: bool,
+}
+
+impl Sidecar {
+    pub(super) fn new(options: &Options) -> Option<Self> {
+        if options.art_patterns.0.is_empty() {
+            return None;
+        }
+        Some(Self {
+            globs: options.art_patterns.clone(),
+            parent: options.art_search_parent,
+        })
+    }
+
+    /// Returns the best match by pattern, then directory, then resolution.
+    pub(super) fn search(&self, path: &Path) -> Option<PathBuf> {
+        let basename = path.file_stem()?.to_str()?;
+        let dir = path.parent()?;
+        let parent = dir.parent().filter(|_| {
+            self.parent
+                && dir
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(is_disc)
+        });
+        let mut candidates = Vec::new();
+        for (depth, dir) in [Some(dir), parent].into_iter().flatten().enumerate() {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !is_image(&path) || entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    continue;
+                }
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                let Some(rank) = self
+                    .globs
+                    .0
+                    .iter()
+                    .position(|glob| glob.matches(name, basename))
+                else {
+                    continue;
+                };
+                candidates.push((rank, depth, path));
+            }
+        }
+        candidates
+            .into_iter()
+            .map(|(rank, depth, path)| {
+                let pixels =
+                    image::image_dimensions(&path).map_or(0, |(w, h)| u64::from(w) * u64::from(h));
+                ((rank, depth, Reverse(pixels)), path)
+            })
+            .min()
+            .map(|(_, path)| path)
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+}
+
+/// Disc subfolders like `CD1`, `Disc 2` or `disk_03` usually keep the art in the album folder.
+fn is_disc(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let Some(number) = ["cd", "disc", "disk"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+    else {
+        return false;
+    };
+    let number = number.trim_start_matches([' ', '_', '-', '.']);
+    !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+}
@@ -0,0 +1,158 @@
+use crate::plugin::options::Options;
+use image::{DynamicImage, codecs::png::PngEncoder};
+use std::{
+    fs,
+    io::{self, Write},
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use url::Url;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Sizes of the thumbnail spec, largest first, with their maximum width and height.
+const SIZES: [(&str, u32); 4] = [
+    ("xx-large", 1024),
+    ("x-large", 512),
+    ("large", 256),
+    ("normal", 128),
+];
+
+/// The size that generated thumbnails are stored in.
+const GENERATE: (&str, u32) = SIZES[2];
+
+/// Thumbnails under `$XDG_CACHE_HOME/thumbnails` as in the freedesktop thumbnail spec, shared with
+/// file managers.
+#[derive(Clone, Debug)]
+pub(super) struct Thumbnails {
+    dir: PathBuf,
+    pub(super) generate: bool,
+}
+
+impl Thumbnails {
+    pub(super) fn new(options: &Options) -> Option<Self> {
+        if !options.art_thumbnails {
+            return None;
+        }
+        Some(Self {
+            dir: super::cache_dir()?.join("thumbnails"),
+            generate: options.art_thumbnails_generate,
+        })
+    }
+
+    /// Returns the largest thumbnail of `path` that is up to date.
+    pub(super) fn find(&self, path: &Path) -> Option<PathBuf> {
+        let (uri, mtime) = identity(path).ok()?;
+        let name = name(&uri);
+        SIZES
+            .iter()
+            .map(|(size, _)| self.dir.join(size).join(&name))
+            .find(|thumbnail| {
+                let Ok(data) = fs::read(thumbnail) else {
+                    return false;
+                };
+                let text = text(&data);
+                let get = |key: &str| text.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+                get("Thumb::URI") == Some(uri.as_str()) && get("Thumb::MTime") == Some(&*mtime)
+            })
+    }
+
+    /// Stores `image` as the thumbnail of `path`, unless the file changed in the meantime.
+    pub(super) fn store(&self, path: &Path, image: &DynamicImage) -> anyhow::Result<()> {
+        let (uri, mtime) = identity(path)?;
+        let (size, max) = GENERATE;
+        let dir = self.dir.join(size);
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)?;
+        let image = if image.width() > max || image.height() > max {
+            image.thumbnail(max, max)
+        } else {
+            image.clone()
+        };
+        let mut data = Vec::new();
+        image
+            .to_rgba8()
+            .write_with_encoder(PngEncoder::new(&mut data))?;
+        let data = with_text(
+            &data,
+            &[
+                ("Thumb::URI", uri.as_str()),
+                ("Thumb::MTime", &mtime),
+                ("Software", "mpv-mpris2"),
+            ],
+        )?;
+        // written under a temporary name and renamed, so readers never see a partial file
+        let mut file = tempfile::Builder::new()
+            .prefix(".mpv-mpris2-")
+            .permissions(fs::Permissions::from_mode(0o600))
+            .tempfile_in(&dir)?;
+        file.write_all(&data)?;
+        if identity(path)?.1 != mtime {
+            anyhow::bail!("File changed while generating its thumbnail");
+        }
+        file.persist(dir.join(name(&uri)))?;
+        tracing::debug!(path = %path.display(), "Stored thumbnail");
+        Ok(())
+    }
+}
+
+/// The URI and the modification time in seconds, which thumbnails are checked against.
+fn identity(path: &Path) -> io::Result<(Url, String)> {
+    let uri = Url::from_file_path(path).map_err(|()| io::ErrorKind::InvalidInput)?;
+    let mtime = fs::metadata(path)?.modified()?.duration_since(UNIX_EPOCH);
+    Ok((uri, mtime.unwrap_or_default().as_secs().to_string()))
+}
+
+fn name(uri: &Url) -> String {
+    format!("{:x}.png", md5::compute(uri.as_str()))
+}
+
+fn chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE).unwrap_or_default();
+    std::iter::from_fn(move || {
+        let len = usize::try_from(u32::from_be_bytes(rest.get(..4)?.try_into().ok()?)).ok()?;
+        let kind = rest.get(4..8)?;
+        let body = rest.get(8..8usize.checked_add(len)?)?;
+        rest = rest.get(12 + len..)?;
+        Some((kind, body))
+    })
+}
+
+/// The `tEXt` chunks of a PNG file, as key-value pairs.
+fn text(data: &[u8]) -> Vec<(String, String)> {
+    chunks(data)
+        .filter(|(kind, _)| *kind == b"tEXt")
+        .filter_map(|(_, body)| {
+            let nul = body.iter().position(|&b| b == 0)?;
+            // keywords and text are Latin-1
+            let latin1 = |bytes: &[u8]| bytes.iter().copied().map(char::from).collect::<String>();
+            Some((latin1(&body[..nul]), latin1(&body[nul + 1..])))
+        })
+        .collect()
+}
+
+/// Inserts `tEXt` chunks right after the `IHDR` chunk of a PNG file.
+fn with_text(data: &[u8], text: &[(&str, &str)]) -> anyhow::Result<Vec<u8>> {
+    let Some((b"IHDR", ihdr)) = chunks(data).next() else {
+        anyhow::bail!("Not a PNG file");
+    };
+    let split = PNG_SIGNATURE.len() + 12 + ihdr.len();
+    let mut out = data[..split].to_vec();
+    for (key, value) in text {
+        // the values here are URIs and numbers, which are ASCII
+        let mut body = key.as_bytes().to_vec();
+        body.push(0);
+        body.extend_from_slice(value.as_bytes());
+        out.extend_from_slice(&u32::try_from(body.len())?.to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(b"tEXt");
+        out.extend_from_slice(&body);
+        let crc = crc32fast::hash(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+    out.extend_from_slice(&data[split..]);
+    Ok(out)
+}
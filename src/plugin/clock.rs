@@ -0,0 +1,168 @@
+use crate::mpv::{self, Mpv};
+use event_listener::Event;
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The playback position extrapolated from the last known one and the rate it advances at.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Clock {
+    anchor: Option<(Instant, f64)>,
+    rate: f64,
+    /// When the position was last set, samples requested earlier are stale.
+    set_at: Option<Instant>,
+}
+
+/// A `playback-time` sample, with when it was requested and received.
+pub(super) struct Sample {
+    pub(super) time: Option<mpv::Seconds>,
+    pub(super) requested: Instant,
+    pub(super) received: Instant,
+}
+
+impl Clock {
+    pub(crate) fn position(&self, now: Instant) -> Option<f64> {
+        let (instant, position) = self.anchor?;
+        let elapsed = now.saturating_duration_since(instant).as_secs_f64();
+        Some((position + elapsed * self.rate).max(0.0))
+    }
+
+    /// Sets the position, `None` while it is unknown, e.g. between files or while seeking.
+    pub(crate) fn set(&mut self, position: Option<f64>, now: Instant) {
+        self.anchor(position, now);
+        self.set_at = Some(now);
+    }
+
+    fn anchor(&mut self, position: Option<f64>, now: Instant) {
+        self.anchor = position.map(|position| (now, position));
+    }
+
+    /// Changes the rate from now on, `0` while paused.
+    pub(crate) fn set_rate(&mut self, rate: f64, now: Instant) {
+        let position = self.position(now);
+        self.anchor(position, now);
+        self.rate = rate;
+    }
+
+    /// Sets the position to an observed one, returning it if it is off from the extrapolated one
+    /// by more than `threshold` seconds, i.e. if playback jumped without a seek. Samples requested
+    /// before a seek are dropped, they may predate it.
+    pub(super) fn observe(&mut self, sample: Sample, threshold: f64) -> Option<f64> {
+        if self.set_at.is_some_and(|set_at| sample.requested < set_at) {
+            return None;
+        }
+        let position = sample.time.map(f64::from);
+        let now = sample.received;
+        let expected = self.position(now);
+        self.anchor(position, now);
+        match (expected, position) {
+            (Some(expected), Some(position))
+                if threshold > 0.0 && (position - expected).abs() > threshold =>
+            {
+                Some(position)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether playback advances, for tasks that have nothing to do while it doesn't.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Playing(Rc<(Cell<bool>, Event)>);
+
+impl Playing {
+    pub(super) fn set(&self, playing: bool) {
+        let (current, event) = &*self.0;
+        if !current.replace(playing) && playing {
+            event.notify(usize::MAX);
+        }
+    }
+
+    /// Returns once playback advances.
+    pub(super) async fn wait(&self) {
+        let (current, event) = &*self.0;
+        while !current.get() {
+            let listener = event.listen();
+            if current.get() {
+                break;
+            }
+            listener.await;
+        }
+    }
+}
+
+/// Samples `playback-time` periodically while playing, for jumps that don't come with a seek, like
+/// `loop-file` wrapping around or A-B loops. The position can't drift otherwise.
+pub(super) async fn poll(mpv: Mpv, playing: Playing, tx: kanal::AsyncSender<Sample>) {
+    loop {
+        smol::Timer::after(POLL_INTERVAL).await;
+        playing.wait().await;
+        let requested = Instant::now();
+        // unavailable while idle
+        let time = mpv.get_property("playback-time").await.ok().flatten();
+        let sample = Sample {
+            time,
+            requested,
+            received: Instant::now(),
+        };
+        if tx.send(sample).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smol::future::poll_once;
+    use std::pin::pin;
+
+    fn sample(time: f64, requested: Instant, received: Instant) -> Sample {
+        Sample {
+            time: Some(time.into()),
+            requested,
+            received,
+        }
+    }
+
+    #[test]
+    fn jump_is_reported() {
+        let start = Instant::now();
+        let mut clock = Clock::default();
+        clock.set(Some(10.0), start);
+        clock.set_rate(1.0, start);
+        let now = start + Duration::from_secs(2);
+        assert_eq!(clock.observe(sample(12.0, now, now), 1.0), None);
+        assert_eq!(clock.observe(sample(0.0, now, now), 1.0), Some(0.0));
+        assert_eq!(clock.position(now), Some(0.0));
+    }
+
+    #[test]
+    fn sample_requested_before_seek_is_dropped() {
+        let start = Instant::now();
+        let mut clock = Clock::default();
+        clock.set_rate(1.0, start);
+        let seeked = start + Duration::from_secs(1);
+        clock.set(Some(50.0), seeked);
+        let received = start + Duration::from_secs(2);
+        assert_eq!(clock.observe(sample(1.0, start, received), 1.0), None);
+        assert_eq!(clock.position(received), Some(51.0));
+    }
+
+    #[test]
+    fn playing_wakes_waiters() {
+        let playing = Playing::default();
+        let mut wait = pin!(playing.wait());
+        smol::block_on(async {
+            assert!(poll_once(&mut wait).await.is_none());
+            playing.set(false);
+            assert!(poll_once(&mut wait).await.is_none());
+            playing.set(true);
+            assert!(poll_once(&mut wait).await.is_some());
+        });
+    }
+}
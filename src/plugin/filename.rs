@@ -0,0 +1,199 @@
+use compact_str::CompactString;
+use std::{
+    iter,
+    path::{Component, Path},
+    str::FromStr,
+};
+use strum::EnumString;
+
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Field {
+    Album,
+    AlbumArtist,
+    Artist,
+    Disc,
+    Genre,
+    Title,
+    Track,
+}
+
+impl Field {
+    const fn is_numeric(self) -> bool {
+        matches!(self, Self::Disc | Self::Track)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Literal(CompactString),
+    /// Unknown names, e.g. `{_}`, match anything and are discarded.
+    Field(Option<Field>),
+}
+
+/// A `/`-separated path pattern like `{artist}/{album}/{track} - {title}`, matched against the
+/// trailing components of a path. The last component is matched without its extension.
+#[derive(Clone, Debug)]
+pub(crate) struct Pattern(Vec<Vec<Token>>);
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let components = s.split('/').map(|mut s| {
+            let mut tokens = Vec::new();
+            while !s.is_empty() {
+                if let Some(rest) = s.strip_prefix('{') {
+                    let Some((name, rest)) = rest.split_once('}') else {
+                        anyhow::bail!("Unclosed '{{' in filename pattern");
+                    };
+                    tokens.push(Token::Field(name.parse().ok()));
+                    s = rest;
+                } else {
+                    let end = s.find('{').unwrap_or(s.len());
+                    tokens.push(Token::Literal(s[..end].into()));
+                    s = &s[end..];
+                }
+            }
+            Ok(tokens)
+        });
+        Ok(Self(components.collect::<anyhow::Result<_>>()?))
+    }
+}
+
+impl Pattern {
+    fn matches<'a>(&self, path: &'a Path) -> Option<Vec<(Field, &'a str)>> {
+        let names = iter::once(path.file_stem()).chain(
+            path.parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .rev()
+                .map(|component| match component {
+                    Component::Normal(name) => Some(name),
+                    _ => None,
+                }),
+        );
+        let mut fields = Vec::new();
+        let mut components = self.0.iter().rev();
+        for (name, tokens) in names.zip(components.by_ref()) {
+            if !match_tokens(tokens, name?.to_str()?, &mut fields) {
+                return None;
+            }
+        }
+        // the path is shorter than the pattern
+        if components.next().is_some() {
+            return None;
+        }
+        Some(fields)
+    }
+}
+
+fn match_tokens<'a>(tokens: &[Token], s: &'a str, fields: &mut Vec<(Field, &'a str)>) -> bool {
+    match tokens.split_first() {
+        None => s.is_empty(),
+        Some((Token::Literal(literal), rest)) => s
+            .strip_prefix(literal.as_str())
+            .is_some_and(|s| match_tokens(rest, s, fields)),
+        Some((&Token::Field(field), rest)) => {
+            // shortest match first, so `{artist} - {title}` splits at the first separator
+            let ends = s.char_indices().skip(1).map(|(i, _)| i).chain([s.len()]);
+            for end in ends {
+                let (value, s) = s.split_at(end);
+                if field.is_some_and(Field::is_numeric)
+                    && !value.bytes().all(|b| b.is_ascii_digit())
+                {
+                    break;
+                }
+                let len = fields.len();
+                if let Some(field) = field {
+                    fields.push((field, value.trim()));
+                }
+                if match_tokens(rest, s, fields) {
+                    return true;
+                }
+                fields.truncate(len);
+            }
+            false
+        }
+    }
+}
+
+/// A `|`-separated list of [`Pattern`]s. The first pattern that matches is used.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Patterns(Vec<Pattern>);
+
+impl Patterns {
+    pub(crate) fn infer<'a>(&self, path: &'a Path) -> Vec<(Field, &'a str)> {
+        self.0
+            .iter()
+            .find_map(|pattern| pattern.matches(path))
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for Patterns {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let patterns = s.split('|').filter(|pattern| !pattern.is_empty());
+        patterns
+            .map(str::parse)
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(patterns: &str, path: &str) -> Vec<(Field, &'static str)> {
+        let patterns: Patterns = patterns.parse().unwrap();
+        let path = Path::new(path.to_owned().leak());
+        patterns.infer(path)
+    }
+
+    #[test]
+    fn matches() {
+        let fields = infer(
+            "{artist}/{album}/{track} - {title}",
+            "/music/Artist/Album/01 - Title - Live.flac",
+        );
+        // the file name is matched first
+        let expected = [
+            (Field::Track, "01"),
+            (Field::Title, "Title - Live"),
+            (Field::Album, "Album"),
+            (Field::Artist, "Artist"),
+        ];
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn no_match() {
+        // not a number
+        assert!(infer("{track} - {title}", "/music/A - Title.flac").is_empty());
+        // the path is shorter than the pattern
+        assert!(infer("{artist}/{album}/{title}", "Album/Title.flac").is_empty());
+        // literals must match
+        assert!(infer("{artist} - {title}", "/music/Title.flac").is_empty());
+    }
+
+    #[test]
+    fn partial_fields() {
+        let fields = infer("{_}/{artist} - {title}", "/music/Live/Artist - Title.mp3");
+        assert_eq!(fields, [(Field::Artist, "Artist"), (Field::Title, "Title")]);
+    }
+
+    #[test]
+    fn first_matching_pattern_wins() {
+        let patterns = "{track} - {title}|{artist} - {title}";
+        let fields = infer(patterns, "/music/Artist - Title.mp3");
+        assert_eq!(fields, [(Field::Artist, "Artist"), (Field::Title, "Title")]);
+    }
+
+    #[test]
+    fn unclosed_field_is_an_error() {
+        assert!("{artist".parse::<Patterns>().is_err());
+    }
+}
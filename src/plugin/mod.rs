@@ -1,17 +1,25 @@
 use crate::mpv::{self, Mpv};
+use enumflags2::BitFlags;
 use futures_concurrency::stream::Merge;
 use mpris_server::Signal;
 use smol::{LocalExecutor, lock::RwLock, prelude::*};
-use tempfile::NamedTempFile;
-use url::Url;
+use std::time::Instant;
 
 pub(crate) mod args;
 mod art;
+mod clock;
+mod filename;
 mod mpris;
+mod options;
+mod settle;
 mod state;
+mod template;
+mod volume;
+mod ytdl;
 
 pub(crate) struct Player {
     state: RwLock<state::State>,
+    options: options::Options,
     mpv: Mpv,
 }
 
@@ -22,52 +30,114 @@ pub(crate) async fn main_loop(
 ) -> anyhow::Result<()> {
     enum LoopEvent {
         MpvEvents(Vec<mpv::Event>),
-        ArtFile(NamedTempFile),
+        Art(art::Art),
+        Expanded(template::Expanded),
+        PlaybackTime(clock::Sample),
+        Flush(()),
+        Settled(()),
     }
     let events = Mpv::subscribe(handshake_tx)?;
-    let (mut art, art_files) = art::State::new();
+    let playing = clock::Playing::default();
+    let (mut art, arts) = art::State::new(ex, &server.imp().options, playing.clone());
+    let (mut template, expanded) = template::State::new(&server.imp().options);
+    let (mut coalescer, flushes) = mpris::Coalescer::new(&server.imp().options);
+    let (mut settle, settled) = settle::Settle::new(&server.imp().options);
+    let (times_tx, times) = kanal::bounded_async(0);
+    let _poll = ex.spawn(clock::poll(
+        server.imp().mpv.clone(),
+        playing.clone(),
+        times_tx,
+    ));
     let mut events = {
         let events = events.stream().map(LoopEvent::MpvEvents);
-        let art_files = art_files.stream().map(LoopEvent::ArtFile);
-        (events, art_files).merge()
+        let arts = arts.stream().map(LoopEvent::Art);
+        let expanded = expanded.stream().map(LoopEvent::Expanded);
+        let times = times.stream().map(LoopEvent::PlaybackTime);
+        let flushes = flushes.stream().map(LoopEvent::Flush);
+        let settled = settled.stream().map(LoopEvent::Settled);
+        (events, arts, expanded, times, flushes, settled).merge()
     };
     while let Some(loop_event) = events.next().await {
-        let mut state = server.imp().state().await;
+        // doesn't touch the state, so D-Bus reads aren't held up
+        if let LoopEvent::Flush(()) = loop_event {
+            if let Err(e) = coalescer.flush(server.connection()).await {
+                tracing::error!(error = %e, "Failed to emit changes");
+            }
+            continue;
+        }
+        let mut diff = BitFlags::empty();
         let mut seeked = None;
+        let mut settled = false;
+        // changes are applied in place, the lock is held only while no await is pending
+        let mut state = server.imp().state.write().await;
         match loop_event {
             LoopEvent::MpvEvents(events) => {
                 use mpv::{Event, Property};
+                let mut started = false;
                 for event in events {
                     match event {
                         Event::Shutdown => return Ok(()),
                         Event::StartFile {
                             playlist_entry_id: value,
                         } => {
-                            state.art_url = None;
-                            state.playlist_entry_id = Some(value);
+                            diff |= state.set_art_url(None);
+                            diff |= state.set_expanded(template::Expanded::default());
+                            diff |= state.set_playlist_entry_id(Some(value));
+                            state.clock.set(None, Instant::now());
                             art.clear();
+                            settle.start_file();
+                            started = true;
+                        }
+                        Event::FileLoaded => {
+                            settled |= settle.file_loaded(ex);
                         }
                         Event::EndFile {
                             playlist_entry_id: _,
                             ..
                         } => {
-                            state.playlist_entry_id = None;
+                            diff |= state.set_playlist_entry_id(None);
+                            settled |= settle.settle();
                         }
                         Event::PropertyChange(Property::Known(property)) => {
-                            state.change(property);
+                            diff |= state.change(property);
+                        }
+                        Event::Seek => {
+                            state.clock.set(None, Instant::now());
                         }
                         Event::Seeked { playback_time } => {
+                            state.clock.set(Some(playback_time.into()), Instant::now());
                             seeked = Some(playback_time);
                         }
+                        Event::Restarted { playback_time } => {
+                            state.clock.set(Some(playback_time.into()), Instant::now());
+                        }
                         _ => (),
                     }
                 }
+                // unpaused or another file started without `Play`, e.g. from mpv itself
+                if state.stopped && (!state.pause || started) {
+                    clear(ex, &server.imp().mpv, mpris::STOPPED);
+                }
+                playing.set(state.playing());
+            }
+            LoopEvent::Art(new) => {
+                diff |= state.set_art_url(Some(art.set_art(new)));
+                settled |= settle.art();
+            }
+            LoopEvent::Expanded(expanded) => {
+                diff |= state.set_expanded(expanded);
+                settled |= settle.expanded();
+            }
+            LoopEvent::PlaybackTime(sample) => {
+                let threshold = server.imp().options.seek_threshold;
+                seeked = state.clock.observe(sample, threshold).map(Into::into);
             }
-            LoopEvent::ArtFile(file) => {
-                state.art_url = Url::from_file_path(file.path()).ok();
-                art.set_file(file);
+            LoopEvent::Settled(()) => {
+                settled |= settle.settle();
             }
+            LoopEvent::Flush(()) => unreachable!("flushed above"),
         }
+        drop(state);
         if let Some(playback_time) = seeked.take()
             && let Err(e) = server
                 .emit(Signal::Seeked {
@@ -77,13 +147,43 @@ pub(crate) async fn main_loop(
         {
             tracing::error!(error = %e, "Failed to emit seeked signal");
         }
-        let mut changes = server.imp().update(state).await;
-        if let Some(change) = changes.art() {
-            art.spawn_worker(ex, change);
+        let mut changes = server.imp().update(diff).await;
+        if let Some(tracks) = changes.art() {
+            if art.spawn_worker(ex, tracks, &server.imp().mpv) {
+                settle.wait_for_art();
+            } else {
+                settled |= settle.art();
+            }
+        }
+        if let Some(index) = changes.prefetch() {
+            art.prefetch(ex, index, &server.imp().mpv);
+        }
+        if changes.expand() {
+            if template.spawn_worker(ex, server.imp().mpv.clone()) {
+                settle.wait_for_expansion();
+            } else {
+                settled |= settle.expanded();
+            }
         }
-        if let Err(e) = changes.emit(server.connection()).await {
+        if settle.is_settling() {
+            changes.defer_metadata();
+        } else if settled {
+            changes.settled();
+        }
+        if let Err(e) = coalescer.push(ex, server.connection(), changes).await {
             tracing::error!(error = %e, "Failed to emit changes");
         }
     }
     Ok(())
 }
+
+/// Clears a flag of ours, which is observed like any other property.
+fn clear(ex: &LocalExecutor<'_>, mpv: &Mpv, name: &'static str) {
+    let mpv = mpv.clone();
+    ex.spawn(async move {
+        if let Err(e) = mpv.set_property(name, false).await {
+            tracing::error!(error = %e, name, "Failed to clear flag");
+        }
+    })
+    .detach();
+}
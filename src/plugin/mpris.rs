@@ -1,16 +1,41 @@
-use super::{art, state::StateDiff};
+use super::{
+    art,
+    options::{Options, Separators},
+    state::StateDiff,
+};
 use crate::{future::FutureSyncExt, mpv};
+use enumflags2::BitFlags;
 use mpris_server::{
     LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, RootInterface, Time,
     TrackId, Volume, builder::MetadataBuilder,
 };
 use serde::{Deserialize, Serialize};
-use smol::lock::{OnceCell, RwLockWriteGuard};
-use std::path::PathBuf;
-use std::{borrow::Cow, collections::BTreeMap, mem};
+use smol::lock::OnceCell;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    mem,
+    time::{Duration, Instant},
+};
+use strum::EnumString;
 use url::Url;
 use zbus::{fdo, names::InterfaceName, object_server::Interface, zvariant, zvariant::ObjectPath};
 
+/// Set while [`StopMode::Stopped`] reports `Stopped`, shared through mpv so it is observed like
+/// any other property.
+pub(super) const STOPPED: &str = "user-data/mpv-mpris2/stopped";
+
+#[derive(Clone, Copy, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum StopMode {
+    /// Pause and seek to the start.
+    Rewind,
+    /// mpv's `stop`, keeping the playlist.
+    Idle,
+    /// Like [`Self::Rewind`], reporting `Stopped` until the next `Play`.
+    Stopped,
+}
+
 impl RootInterface for super::Player {
     async fn raise(&self) -> fdo::Result<()> {
         Ok(())
@@ -33,8 +58,9 @@ impl RootInterface for super::Player {
         Ok(self.mpv.set_property("fullscreen", value).sync().await?)
     }
 
+    /// Only with a video output, e.g. not with `--no-video` or for audio files.
     async fn can_set_fullscreen(&self) -> fdo::Result<bool> {
-        Ok(true)
+        Ok(self.state.read().await.vo_configured)
     }
 
     async fn can_raise(&self) -> fdo::Result<bool> {
@@ -122,40 +148,104 @@ impl PlayerInterface for super::Player {
     }
 
     async fn play_pause(&self) -> fdo::Result<()> {
+        if let PlaybackStatus::Stopped = self.state.read().await.playback_status() {
+            return self.play().await;
+        }
         let cmd = mpv::ListCommand::Cycle("pause", None);
         Ok(self.mpv.run_command(cmd).sync().await?)
     }
 
     async fn stop(&self) -> fdo::Result<()> {
         use mpv::SeekMode::*;
+        if let StopMode::Idle = self.options.stop_mode {
+            let cmd = mpv::NamedCommand::Stop {
+                flags: Some(mpv::StopFlags::KeepPlaylist),
+            };
+            return Ok(self.mpv.run_command(cmd).sync().await?);
+        }
         self.pause().await?;
         let cmd = mpv::NamedCommand::Seek {
             target: 0.0.into(),
             flags: Some(Absolute.into()),
         };
-        Ok(self.mpv.run_command(cmd).sync().await?)
+        self.mpv.run_command::<()>(cmd).sync().await?;
+        if let StopMode::Stopped = self.options.stop_mode {
+            self.mpv.set_property(STOPPED, true).sync().await?;
+        }
+        Ok(())
     }
 
+    /// From idle, starts the playlist at the current entry or the first one.
     async fn play(&self) -> fdo::Result<()> {
+        let (idle, stopped, current) = {
+            let state = self.state.read().await;
+            let idle = state.playlist_entry_id.is_none() && state.playlist_count > Some(0);
+            (idle, state.stopped, state.playlist_current_pos)
+        };
+        if stopped {
+            self.mpv.set_property(STOPPED, false).sync().await?;
+        }
+        if idle {
+            let cmd = mpv::NamedCommand::PlaylistPlayIndex {
+                index: current.unwrap_or(0),
+            };
+            self.mpv.run_command::<()>(cmd).sync().await?;
+        }
         Ok(self.mpv.set_property("pause", false).sync().await?)
     }
 
+    /// Seeking past the end goes to the next track, and before the start to the start. Without a
+    /// position, e.g. while idle, there is nothing to seek, nor without `CanSeek`.
     async fn seek(&self, offset: Time) -> fdo::Result<()> {
         use mpv::{SeekMode::*, SeekPrecision::*};
+        let (duration, position) = {
+            let state = self.state.read().await;
+            if !state.seekable {
+                return Ok(());
+            }
+            (
+                f64::from(state.duration),
+                state.clock.position(Instant::now()),
+            )
+        };
+        let position = match position {
+            Some(position) => position,
+            // unavailable while idle
+            None => {
+                let position = self
+                    .mpv
+                    .get_property::<Option<mpv::Seconds>>("playback-time");
+                match position.sync().await {
+                    Ok(Some(position)) => position.into(),
+                    Ok(None) | Err(_) => return Ok(()),
+                }
+            }
+        };
+        let target = position + f64::from(mpv::Seconds::from(offset));
+        if duration > 0.0 && target > duration {
+            return self.next().await;
+        }
         let cmd = mpv::NamedCommand::Seek {
-            target: offset.into(),
-            flags: Some((Relative, Exact).into()),
+            target: target.max(0.0).into(),
+            flags: Some((Absolute, Exact).into()),
         };
         Ok(self.mpv.run_command(cmd).sync().await?)
     }
 
     async fn set_position(&self, track_id: TrackId, position: Time) -> fdo::Result<()> {
-        let id = track_id
-            .strip_prefix("/io/mpv/playlist_entry_id/")
-            .map(str::parse)
-            .transpose()
-            .map_err(|e| fdo::Error::InvalidArgs(format!("Invalid track ID: {e}")))?;
-        if id.is_some() && id == self.state.read().await.playlist_entry_id {
+        let state = self.state.read().await;
+        if let Some(id) = state.track_id()
+            && track_id.as_str() == id
+        {
+            let (duration, seekable) = (state.duration, state.seekable);
+            drop(state);
+            // positions outside of the track are ignored, as the spec says
+            if !seekable
+                || position < Time::ZERO
+                || (f64::from(duration) > 0.0 && position > duration.into())
+            {
+                return Ok(());
+            }
             let value: mpv::Seconds = position.into();
             self.mpv.set_property("playback-time", value).sync().await?;
             return Ok(());
@@ -209,7 +299,11 @@ impl PlayerInterface for super::Player {
         Ok(self.state.read().await.speed)
     }
 
+    /// A rate of `0` pauses, as the spec says.
     async fn set_rate(&self, rate: PlaybackRate) -> zbus::Result<()> {
+        if rate == 0.0 {
+            return Ok(self.mpv.set_property("pause", true).sync().await?);
+        }
         Ok(self.mpv.set_property("speed", rate).sync().await?)
     }
 
@@ -217,35 +311,85 @@ impl PlayerInterface for super::Player {
         Ok(self.state.read().await.shuffle)
     }
 
+    /// The `shuffle` option only applies to playlists loaded later, so the queue is shuffled too.
+    /// The option follows, so `Shuffle` also picks up `--shuffle` and playlists shuffled on load.
     async fn set_shuffle(&self, shuffle: bool) -> zbus::Result<()> {
+        if shuffle {
+            let cmd = mpv::NamedCommand::PlaylistShuffle;
+            self.mpv.run_command::<()>(cmd).sync().await?;
+            // the current entry keeps playing and moves to the front, so the rest of the queue
+            // comes after it. The position is -1 without one.
+            let current: i64 = self.mpv.get_property("playlist-current-pos").sync().await?;
+            if let Ok(current) = u64::try_from(current)
+                && current > 0
+            {
+                let cmd = mpv::NamedCommand::PlaylistMove {
+                    index1: current,
+                    index2: 0,
+                };
+                self.mpv.run_command::<()>(cmd).sync().await?;
+            }
+        } else {
+            let cmd = mpv::NamedCommand::PlaylistUnshuffle;
+            self.mpv.run_command::<()>(cmd).sync().await?;
+        }
         Ok(self.mpv.set_property("shuffle", shuffle).sync().await?)
     }
 
     async fn metadata(&self) -> fdo::Result<Metadata> {
         let state = self.state.read().await;
-        state.metadata().map_err(fdo::Error::Failed)
+        state.metadata(&self.options).map_err(fdo::Error::Failed)
     }
 
     async fn volume(&self) -> fdo::Result<Volume> {
-        Ok(self.state.read().await.volume())
+        Ok(self.state.read().await.volume(&self.options))
     }
 
+    /// Setting a volume unmutes, unless it is `0` and that mutes.
     async fn set_volume(&self, volume: Volume) -> zbus::Result<()> {
-        let volume = volume * 100.0;
-        Ok(self.mpv.set_property("volume", volume).sync().await?)
+        let volume = volume.max(0.0);
+        if volume == 0.0 && self.options.volume_zero_mutes {
+            return Ok(self.mpv.set_property("mute", true).sync().await?);
+        }
+        let max = self.state.read().await.volume_max;
+        let volume = self.options.volume_curve.to_mpv(volume, max);
+        self.mpv.set_property("volume", volume).sync().await?;
+        Ok(self.mpv.set_property("mute", false).sync().await?)
     }
 
+    /// Shells poll this often, so it comes from the clock unless the position is unknown. The
+    /// clock may run past the end until mpv notices it, so it stops at the duration.
     async fn position(&self) -> fdo::Result<Time> {
-        let position: mpv::Seconds = self.mpv.get_property("playback-time").sync().await?;
-        Ok(position.into())
+        let state = self.state.read().await;
+        if state.idle_active {
+            return Ok(Time::ZERO);
+        }
+        if let Some(mut position) = state.clock.position(Instant::now()) {
+            let duration = f64::from(state.duration);
+            if duration > 0.0 {
+                position = position.min(duration);
+            }
+            return Ok(mpv::Seconds::from(position).into());
+        }
+        drop(state);
+        // unavailable while idle
+        let position = self
+            .mpv
+            .get_property::<Option<mpv::Seconds>>("playback-time");
+        Ok(position
+            .sync()
+            .await
+            .ok()
+            .flatten()
+            .map_or(Time::ZERO, Time::from))
     }
 
     async fn minimum_rate(&self) -> fdo::Result<PlaybackRate> {
-        Ok(0.01)
+        Ok(self.state.read().await.speed_bounds.0)
     }
 
     async fn maximum_rate(&self) -> fdo::Result<PlaybackRate> {
-        Ok(100.0)
+        Ok(self.state.read().await.speed_bounds.1)
     }
 
     async fn can_go_next(&self) -> fdo::Result<bool> {
@@ -257,7 +401,7 @@ impl PlayerInterface for super::Player {
     }
 
     async fn can_play(&self) -> fdo::Result<bool> {
-        Ok(self.state.read().await.playlist_entry_id.is_some())
+        Ok(self.state.read().await.can_play())
     }
 
     async fn can_pause(&self) -> fdo::Result<bool> {
@@ -274,19 +418,39 @@ impl PlayerInterface for super::Player {
 }
 
 impl super::state::State {
+    /// `loop-playlist` wraps around in both directions.
     pub(super) fn playlist_has_next(&self) -> bool {
-        self.playlist_current_pos
-            .and_then(|x| x.checked_add(1))
-            .zip(self.playlist_count)
-            .is_some_and(|(current, count)| current < count)
+        let wraps = self.loop_playlist && self.playlist_count.is_some_and(|count| count > 0);
+        wraps
+            || self
+                .playlist_current_pos
+                .and_then(|x| x.checked_add(1))
+                .zip(self.playlist_count)
+                .is_some_and(|(current, count)| current < count)
+    }
+
+    /// The entry after the current one, wrapping around with `loop-playlist`.
+    pub(super) fn playlist_next(&self) -> Option<u64> {
+        let (current, count) = self.playlist_current_pos.zip(self.playlist_count)?;
+        match current.checked_add(1)? {
+            next if next < count => Some(next),
+            _ if self.loop_playlist && count > 1 => Some(0),
+            _ => None,
+        }
     }
 
     pub(super) fn playlist_has_previous(&self) -> bool {
-        self.playlist_current_pos.is_some_and(|current| 0 < current)
+        let wraps = self.loop_playlist && self.playlist_count.is_some_and(|count| count > 0);
+        wraps || self.playlist_current_pos.is_some_and(|current| 0 < current)
+    }
+
+    /// A file is loaded, or there is a playlist to start.
+    pub(super) fn can_play(&self) -> bool {
+        self.playlist_entry_id.is_some() || self.playlist_count > Some(0)
     }
 
     pub(super) fn playback_status(&self) -> PlaybackStatus {
-        if self.idle_active || self.eof_reached {
+        if self.idle_active || self.eof_reached || (self.stopped && self.pause) {
             PlaybackStatus::Stopped
         } else if self.pause {
             PlaybackStatus::Paused
@@ -305,61 +469,129 @@ impl super::state::State {
         }
     }
 
-    pub(super) fn metadata(&self) -> Result<Metadata, String> {
-        let track_id = ObjectPath::from_string_unchecked({
-            let Some(playlist_entry_id) = self.playlist_entry_id else {
-                return Ok(MetadataBuilder::default()
-                    .trackid(ObjectPath::from_string_unchecked(
-                        "/org/mpris/MediaPlayer2/TrackList/NoTrack".into(),
-                    ))
-                    .build());
-            };
+    pub(super) fn track_id(&self) -> Option<String> {
+        let playlist_entry_id = self.playlist_entry_id?;
+        Some(if self.metadata.contains_key(&mpv::MetadataKey::IcyTitle) {
+            // each new stream title is its own track
+            let serial = self.icy_title_serial;
+            format!("/io/mpv/playlist_entry_id/{playlist_entry_id}/icy_title/{serial}")
+        } else {
             format!("/io/mpv/playlist_entry_id/{playlist_entry_id}")
-        });
-        let url = match (&self.path, &self.working_directory) {
-            (Some(mpv::Path::Url(url)), _) => Some(url.clone()),
-            (Some(mpv::Path::Path(path)), working_directory) => {
-                let path = if let Some(working_directory) = working_directory {
-                    Cow::Owned(working_directory.join(path))
-                } else {
-                    Cow::Borrowed(path.as_path())
-                };
-                Url::from_file_path(path).ok()
+        })
+    }
+
+    pub(super) fn metadata(&self, options: &Options) -> Result<Metadata, String> {
+        let Some(track_id) = self.track_id() else {
+            return Ok(MetadataBuilder::default()
+                .trackid(ObjectPath::from_string_unchecked(
+                    "/org/mpris/MediaPlayer2/TrackList/NoTrack".into(),
+                ))
+                .build());
+        };
+        let track_id = ObjectPath::from_string_unchecked(track_id);
+        let path = match (&self.path, &self.working_directory) {
+            (Some(mpv::Path::Path(path)), Some(working_directory)) => {
+                Some(Cow::Owned(working_directory.join(path)))
             }
+            (Some(mpv::Path::Path(path)), None) => Some(Cow::Borrowed(path.as_path())),
+            _ => None,
+        };
+        let url = match (&self.path, &path) {
+            (Some(mpv::Path::Url(url)), _) => Some(url.clone()),
+            (_, Some(path)) => Url::from_file_path(path).ok(),
             _ => None,
         };
+        let integer = |s: &str| s.split_once('/').map(|(s, _)| s).unwrap_or(s).parse().ok();
+        let list = |separators: &Separators, s: &str| {
+            Some(separators.split(s).map(str::to_owned).collect::<Vec<_>>())
+        };
         let mut metadata = MetadataBuilder::default()
             .trackid(track_id)
             .length(self.duration.into())
             .title(self.media_title.to_owned())
             .build();
+        let ytdl = self.ytdl.as_ref().filter(|ytdl| ytdl.matches(&self.path));
         metadata.set_art_url(self.art_url.clone());
-        metadata.set_url(url);
+        metadata.set_url(ytdl.and_then(|ytdl| ytdl.url()).or(url));
+        if let Some(ytdl) = ytdl {
+            metadata.set_artist(ytdl.artist().map(|artist| [artist]));
+            metadata.set_content_created(ytdl.content_created());
+        }
+        // inferred values are overwritten by real tags below
+        let inferred = path
+            .as_deref()
+            .map(|path| options.filename_patterns.infer(path));
+        for (field, v) in inferred.into_iter().flatten() {
+            use super::filename::Field::*;
+            match field {
+                Album => metadata.set_album(v.into()),
+                AlbumArtist => {
+                    metadata.set_album_artist(list(&options.album_artist_separators, v));
+                }
+                Artist => metadata.set_artist(list(&options.artist_separators, v)),
+                Disc => metadata.set_disc_number(integer(v)),
+                Genre => metadata.set_genre(list(&options.genre_separators, v)),
+                Title if !self.metadata.contains_key(&mpv::MetadataKey::Title) => {
+                    metadata.set_title(v.into());
+                }
+                Title => (),
+                Track => metadata.set_track_number(integer(v)),
+            }
+        }
         for (k, v) in &self.metadata {
             use crate::mpv::MetadataKey::*;
-            let integer = |s: &str| s.split_once('/').map(|(s, _)| s).unwrap_or(s).parse().ok();
             match (k, v) {
                 (Album, v) => metadata.set_album(v.into()),
-                (AlbumArtist, v) => metadata.set_album_artist([v].into()),
-                (Artist, v) => metadata.set_artist([v].into()),
+                (AlbumArtist, v) => {
+                    metadata.set_album_artist(list(&options.album_artist_separators, v));
+                }
+                (Artist, v) => metadata.set_artist(list(&options.artist_separators, v)),
                 (Bpm, v) => metadata.set_audio_bpm(integer(v)),
-                (Comment, v) => metadata.set_comment([v].into()),
-                (Composer, v) => metadata.set_composer([v].into()),
+                (Comment, v) => metadata.set_comment(list(&options.comment_separators, v)),
+                (Composer, v) => metadata.set_composer(list(&options.composer_separators, v)),
                 (Disc, v) => metadata.set_disc_number(integer(v)),
-                (Genre, v) => metadata.set_genre([v].into()),
-                (Lyricist, v) => metadata.set_lyricist([v].into()),
+                (Genre, v) => metadata.set_genre(list(&options.genre_separators, v)),
+                (Lyricist, v) => metadata.set_lyricist(list(&options.lyricist_separators, v)),
                 (Track, v) => metadata.set_track_number(integer(v)),
+                (IcyTitle, v) => match options.icy_title_separators.split_once(v) {
+                    Some((artist, title)) => {
+                        if metadata.artist().is_none() {
+                            metadata.set_artist(list(&options.artist_separators, artist));
+                        }
+                        metadata.set_title(title.into());
+                    }
+                    None => metadata.set_title(v.into()),
+                },
+                (IcyName, v) if metadata.album().is_none() => metadata.set_album(v.into()),
+                (IcyGenre, v) if metadata.genre().is_none() => {
+                    metadata.set_genre(list(&options.genre_separators, v));
+                }
+                (IcyUrl, v) if metadata.comment().is_none() => metadata.set_comment([v].into()),
                 (Other(k), v) if k.to_ascii_lowercase().starts_with("lyrics") => {
                     metadata.set_lyrics(v.into());
                 }
                 _ => (),
             }
         }
+        let expanded = &self.expanded;
+        if let Some(title) = &expanded.title {
+            metadata.set_title(title.into());
+        }
+        if let Some(artist) = &expanded.artist {
+            metadata.set_artist(list(&options.artist_separators, artist));
+        }
+        if let Some(album) = &expanded.album {
+            metadata.set_album(album.into());
+        }
         Ok(metadata)
     }
 
-    pub(super) fn volume(&self) -> Volume {
-        self.volume as Volume / 100.0
+    /// `0` while muted.
+    pub(super) fn volume(&self, options: &Options) -> Volume {
+        if self.mute {
+            return 0.0;
+        }
+        options.volume_curve.to_mpris(self.volume, self.volume_max)
     }
 }
 
@@ -373,6 +605,21 @@ impl InterfaceChanges {
     fn is_empty(&self) -> bool {
         self.changed.is_empty() && self.invalid.is_empty()
     }
+
+    /// Later changes win, a property is either changed or invalidated.
+    fn merge(&mut self, other: Self) {
+        for (property, value) in other.changed {
+            self.invalid.retain(|p| *p != property);
+            self.changed.insert(property, value);
+        }
+        for property in other.invalid {
+            self.changed.remove(&property);
+            if !self.invalid.contains(&property) {
+                self.invalid.push(property);
+            }
+        }
+    }
+
     async fn emit(
         &self,
         connection: &zbus::Connection,
@@ -397,7 +644,11 @@ impl InterfaceChanges {
 pub(crate) struct PropertyChanges {
     root: InterfaceChanges,
     player: InterfaceChanges,
-    art: Option<(PathBuf, u64)>,
+    art: Option<Vec<art::Track>>,
+    prefetch: Option<u64>,
+    expand: bool,
+    /// Changes shells should see right away, like the playback status or a new track.
+    urgent: bool,
 }
 
 impl PropertyChanges {
@@ -411,10 +662,36 @@ impl PropertyChanges {
         Ok(())
     }
 
-    pub(super) fn art(&mut self) -> Option<(PathBuf, u64)> {
+    fn is_empty(&self) -> bool {
+        self.root.is_empty() && self.player.is_empty()
+    }
+
+    pub(super) fn art(&mut self) -> Option<Vec<art::Track>> {
         self.art.take()
     }
 
+    pub(super) fn prefetch(&mut self) -> Option<u64> {
+        self.prefetch.take()
+    }
+
+    pub(super) fn expand(&self) -> bool {
+        self.expand
+    }
+
+    /// Drops the `Metadata` invalidation while a file is settling, see [`super::settle`].
+    pub(super) fn defer_metadata(&mut self) {
+        self.player.invalid.retain(|p| *p != Property::Metadata);
+    }
+
+    /// Announces the complete `Metadata` of a file that settled.
+    pub(super) fn settled(&mut self) {
+        self.player.changed.remove(&Property::Metadata);
+        if !self.player.invalid.contains(&Property::Metadata) {
+            self.invalidate(Property::Metadata);
+        }
+        self.urgent = true;
+    }
+
     fn change(
         &mut self,
         property: Property,
@@ -438,49 +715,111 @@ impl PropertyChanges {
     }
 }
 
+/// Merges changes so that shells get at most one `PropertiesChanged` per interface and window.
+pub(super) struct Coalescer {
+    pending: PropertyChanges,
+    window: Duration,
+    last: Option<Instant>,
+    timer: Option<smol::Task<()>>,
+    tx: kanal::AsyncSender<()>,
+}
+
+impl Coalescer {
+    pub(super) fn new(options: &Options) -> (Self, kanal::AsyncReceiver<()>) {
+        let (tx, rx) = kanal::bounded_async(0);
+        let window = match options.signal_rate {
+            0 => Duration::ZERO,
+            rate => Duration::from_secs(1) / rate,
+        };
+        let this = Self {
+            pending: PropertyChanges::default(),
+            window,
+            last: None,
+            timer: None,
+            tx,
+        };
+        (this, rx)
+    }
+
+    /// Emits right away if `changes` are urgent or the window has passed, otherwise the
+    /// receiver gets a message when it is time to [`Self::flush`].
+    pub(super) async fn push(
+        &mut self,
+        ex: &smol::LocalExecutor<'_>,
+        connection: &zbus::Connection,
+        changes: PropertyChanges,
+    ) -> zbus::Result<()> {
+        let urgent = changes.urgent;
+        self.pending.root.merge(changes.root);
+        self.pending.player.merge(changes.player);
+        let now = Instant::now();
+        let due = self.last.map_or(now, |last| last + self.window);
+        if urgent || due <= now {
+            return self.flush(connection).await;
+        }
+        if self.timer.is_none() && !self.pending.is_empty() {
+            let tx = self.tx.clone();
+            self.timer = Some(ex.spawn(async move {
+                smol::Timer::at(due).await;
+                _ = tx.send(()).await;
+            }));
+        }
+        Ok(())
+    }
+
+    pub(super) async fn flush(&mut self, connection: &zbus::Connection) -> zbus::Result<()> {
+        self.timer = None;
+        let pending = mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return Ok(());
+        }
+        self.last = Some(Instant::now());
+        pending.emit(connection).await
+    }
+}
+
 impl super::Player {
-    pub(crate) async fn update(&self, other: super::state::State) -> PropertyChanges {
+    pub(super) async fn update(&self, diff: BitFlags<StateDiff>) -> PropertyChanges {
         use Property as P;
         use StateDiff as S;
 
         let mut ret = PropertyChanges::default();
-        let mut state = self.state.write().await;
-        let other = mem::replace(&mut *state, other);
-
-        if let (state_art, other_art) = (
-            art::find(&state.track_list, &state.path, &state.working_directory),
-            art::find(&other.track_list, &other.path, &other.working_directory),
-        ) && state_art != other_art
-        {
-            match state_art {
-                Some(art::Track::Embedded(path, index)) => {
-                    ret.art = Some((path, index));
-                }
-                Some(art::Track::External(url)) => {
-                    state.art_url = url.into();
-                }
-                _ => (),
-            }
+        let state = self.state.read().await;
+        if diff.intersects(S::TrackList | S::Path | S::WorkingDirectory | S::Ytdl) {
+            let ytdl = state.ytdl.as_ref().filter(|ytdl| ytdl.matches(&state.path));
+            ret.art = Some(art::find(
+                &state.track_list,
+                &state.path,
+                &state.working_directory,
+                ytdl.and_then(mpv::YtdlInfo::art_url),
+            ));
         }
-
-        let state = RwLockWriteGuard::downgrade(state);
-        let diff = state.diff(&other);
         if diff.contains(S::Fullscreen) {
             ret.change(P::Fullscreen, state.fullscreen.into());
         }
+        if diff.contains(S::VoConfigured) {
+            ret.change(P::CanSetFullscreen, state.vo_configured.into());
+        }
         if diff.contains(S::PlaylistEntryId) {
-            ret.change(P::CanPlay, state.playlist_entry_id.is_some().into());
+            ret.urgent = true;
             ret.change(P::CanPause, state.playlist_entry_id.is_some().into());
         }
         if diff.contains(S::Seekable) {
             ret.change(P::CanSeek, state.seekable.into());
         }
-        if diff.intersects(S::PlaylistCurrentPos | S::PlaylistCount) {
+        if diff.intersects(S::PlaylistCurrentPos | S::PlaylistCount | S::LoopPlaylist) {
             ret.change(P::CanGoNext, state.playlist_has_next().into());
             ret.change(P::CanGoPrevious, state.playlist_has_previous().into());
         }
-        if diff.intersects(S::IdleActive | S::EofReached | S::Pause) {
+        if diff.intersects(S::PlaylistCurrentPos | S::PlaylistCount | S::LoopPlaylist) {
+            ret.prefetch = state.playlist_next();
+        }
+        if diff.intersects(S::PlaylistEntryId | S::PlaylistCount) {
+            ret.change(P::CanPlay, state.can_play().into());
+        }
+        if diff.intersects(S::IdleActive | S::EofReached | S::Pause | S::Stopped) {
             ret.change(P::PlaybackStatus, state.playback_status().into());
+            ret.urgent = true;
         }
         if diff.intersects(S::LoopFile | S::LoopPlaylist) {
             ret.change(P::LoopStatus, state.loop_status().into());
@@ -491,8 +830,8 @@ impl super::Player {
         if diff.contains(S::Shuffle) {
             ret.change(P::Shuffle, state.shuffle.into());
         }
-        if diff.contains(S::Volume) {
-            ret.change(P::Volume, state.volume().into());
+        if diff.intersects(S::Volume | S::VolumeMax | S::Mute) {
+            ret.change(P::Volume, state.volume(&self.options).into());
         }
         if diff.intersects(
             S::PlaylistEntryId
@@ -500,11 +839,18 @@ impl super::Player {
                 | S::MediaTitle
                 | S::Metadata
                 | S::ArtUrl
+                | S::Ytdl
+                | S::Expanded
                 | S::Path
                 | S::WorkingDirectory,
         ) {
             ret.invalidate(P::Metadata);
         }
+        if diff.intersects(
+            S::PlaylistEntryId | S::MediaTitle | S::Metadata | S::Chapter | S::Duration | S::Path,
+        ) {
+            ret.expand = true;
+        }
         ret
     }
 }
@@ -565,3 +911,237 @@ impl From<mpv::Seconds> for Time {
         Self::from_micros((f64::from(time) * 1_000_000.0) as i64)
     }
 }
+
+/// The spec's rules for the player interface, checked against a fake mpv on the other end of the
+/// IPC socket.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{Player, state::State};
+    use serde_json::{Value, json};
+    use smol::{
+        LocalExecutor,
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        lock::RwLock,
+        net::unix::UnixStream,
+        prelude::*,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Answers `get_property` from `properties`, with mpv's error for the others, and records
+    /// every command but those.
+    async fn fake_mpv(stream: UnixStream, properties: Value, commands: Rc<RefCell<Vec<Value>>>) {
+        let mut lines = BufReader::new(stream.clone()).lines();
+        let mut stream = stream;
+        while let Some(Ok(line)) = lines.next().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let command = &request["command"];
+            let mut response = if command[0] == "get_property" {
+                match properties.get(command[1].as_str().unwrap()) {
+                    Some(data) => json!({ "error": "success", "data": data }),
+                    None => json!({ "error": "property unavailable" }),
+                }
+            } else {
+                commands.borrow_mut().push(command.clone());
+                json!({ "error": "success" })
+            };
+            response["request_id"] = request["request_id"].clone();
+            let response = format!("{response}\n");
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    /// Runs `test` against a player in `state`, returning the commands it sent.
+    fn run(
+        state: State,
+        options: Options,
+        properties: Value,
+        test: impl AsyncFnOnce(&Player),
+    ) -> Vec<Value> {
+        let ex = LocalExecutor::new();
+        let (stream, other) = UnixStream::pair().unwrap();
+        let (mpv, _handshake_tx) = mpv::Mpv::new(&ex, stream);
+        let commands = Rc::default();
+        ex.spawn(fake_mpv(other, properties, Rc::clone(&commands)))
+            .detach();
+        let player = Player {
+            state: RwLock::new(state),
+            options,
+            mpv,
+        };
+        smol::block_on(ex.run(test(&player)));
+        commands.take()
+    }
+
+    fn playing() -> State {
+        State {
+            playlist_entry_id: Some(1),
+            seekable: true,
+            duration: 100.0.into(),
+            speed: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn seek_past_end_goes_to_next() {
+        let commands = run(
+            playing(),
+            Options::default(),
+            json!({ "playback-time": 95.0 }),
+            async |player| player.seek(Time::from_secs(10)).await.unwrap(),
+        );
+        assert_eq!(commands, [json!({ "name": "playlist-next" })]);
+    }
+
+    #[test]
+    fn seek_before_start_clamps_to_start() {
+        let commands = run(
+            playing(),
+            Options::default(),
+            json!({ "playback-time": 5.0 }),
+            async |player| player.seek(Time::from_secs(-10)).await.unwrap(),
+        );
+        let seek = json!({ "name": "seek", "target": 0.0, "flags": "absolute+exact" });
+        assert_eq!(commands, [seek]);
+    }
+
+    #[test]
+    fn seek_without_position_is_ignored() {
+        let commands = run(playing(), Options::default(), json!({}), async |player| {
+            player.seek(Time::from_secs(10)).await.unwrap()
+        });
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn set_position_outside_of_track_is_ignored() {
+        let track_id = TrackId::try_from("/io/mpv/playlist_entry_id/1").unwrap();
+        let commands = run(playing(), Options::default(), json!({}), async |player| {
+            for position in [101, -1, 50].map(Time::from_secs) {
+                let track_id = track_id.clone();
+                player.set_position(track_id, position).await.unwrap();
+            }
+        });
+        assert_eq!(commands, [json!(["set_property", "playback-time", 50.0])]);
+    }
+
+    #[test]
+    fn seeking_without_can_seek_is_ignored() {
+        let state = State {
+            seekable: false,
+            ..playing()
+        };
+        let track_id = TrackId::try_from("/io/mpv/playlist_entry_id/1").unwrap();
+        let commands = run(
+            state,
+            Options::default(),
+            json!({ "playback-time": 95.0 }),
+            async |player| {
+                player.seek(Time::from_secs(10)).await.unwrap();
+                player.seek(Time::from_secs(-10)).await.unwrap();
+                let result = player.set_position(track_id, Time::from_secs(50)).await;
+                result.unwrap();
+            },
+        );
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn set_position_of_other_track_fails() {
+        let track_id = TrackId::try_from("/io/mpv/playlist_entry_id/2").unwrap();
+        run(playing(), Options::default(), json!({}), async |player| {
+            let result = player.set_position(track_id, Time::from_secs(50)).await;
+            assert!(matches!(result, Err(fdo::Error::InvalidArgs(_))));
+        });
+    }
+
+    #[test]
+    fn shuffle_keeps_current_entry_first() {
+        let commands = run(
+            playing(),
+            Options::default(),
+            json!({ "playlist-current-pos": 2 }),
+            async |player| player.set_shuffle(true).await.unwrap(),
+        );
+        assert_eq!(
+            commands,
+            [
+                json!({ "name": "playlist-shuffle" }),
+                json!({ "name": "playlist-move", "index1": 2, "index2": 0 }),
+                json!(["set_property", "shuffle", true]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unshuffle_clears_option() {
+        let commands = run(playing(), Options::default(), json!({}), async |player| {
+            player.set_shuffle(false).await.unwrap()
+        });
+        assert_eq!(
+            commands,
+            [
+                json!({ "name": "playlist-unshuffle" }),
+                json!(["set_property", "shuffle", false]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rate_zero_pauses() {
+        let commands = run(playing(), Options::default(), json!({}), async |player| {
+            player.set_rate(0.0).await.unwrap()
+        });
+        assert_eq!(commands, [json!(["set_property", "pause", true])]);
+    }
+
+    #[test]
+    fn negative_volume_clamps() {
+        let state = State {
+            volume_max: 100.0,
+            ..playing()
+        };
+        let commands = run(state, Options::default(), json!({}), async |player| {
+            player.set_volume(-0.5).await.unwrap()
+        });
+        let expected = [
+            json!(["set_property", "volume", 0.0]),
+            json!(["set_property", "mute", false]),
+        ];
+        assert_eq!(commands, expected);
+    }
+
+    #[test]
+    fn position_is_zero_when_idle() {
+        let state = State {
+            idle_active: true,
+            ..Default::default()
+        };
+        let commands = run(
+            state,
+            Options::default(),
+            json!({ "playback-time": 5.0 }),
+            async |player| assert_eq!(player.position().await.unwrap(), Time::ZERO),
+        );
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn position_stops_at_end() {
+        let mut state = playing();
+        let start = Instant::now() - Duration::from_secs(10);
+        state.clock.set(Some(95.0), start);
+        state.clock.set_rate(1.0, start);
+        run(state, Options::default(), json!({}), async |player| {
+            assert_eq!(player.position().await.unwrap(), Time::from_secs(100))
+        });
+    }
+
+    #[test]
+    fn position_is_zero_without_time() {
+        run(playing(), Options::default(), json!({}), async |player| {
+            assert_eq!(player.position().await.unwrap(), Time::ZERO)
+        });
+    }
+}
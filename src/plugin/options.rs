@@ -0,0 +1,281 @@
+use super::{
+    art::{normalize::Format, publish::UrlMode, sidecar::Globs},
+    filename::Patterns,
+    mpris::StopMode,
+    volume::Curve,
+};
+use crate::mpv::{self, Mpv};
+use compact_str::CompactString;
+use derive_deftly::{Deftly, derive_deftly_adhoc};
+use std::{cmp::Reverse, collections::BTreeMap, convert::Infallible, iter, mem, str::FromStr};
+
+#[derive(Debug, Deftly)]
+#[derive_deftly_adhoc]
+pub(crate) struct Options {
+    pub(crate) artist_separators: Separators,
+    pub(crate) album_artist_separators: Separators,
+    pub(crate) composer_separators: Separators,
+    pub(crate) lyricist_separators: Separators,
+    pub(crate) genre_separators: Separators,
+    pub(crate) comment_separators: Separators,
+    pub(crate) icy_title_separators: Separators,
+    pub(crate) filename_patterns: Patterns,
+    pub(crate) title_template: CompactString,
+    pub(crate) artist_template: CompactString,
+    pub(crate) album_template: CompactString,
+    pub(crate) seek_threshold: f64,
+    pub(crate) signal_rate: u32,
+    pub(crate) settle_timeout: u64,
+    pub(crate) volume_curve: Curve,
+    pub(crate) volume_zero_mutes: bool,
+    pub(crate) stop_mode: StopMode,
+    pub(crate) art_cache: bool,
+    pub(crate) art_cache_size: u64,
+    pub(crate) art_cache_age: u64,
+    pub(crate) art_frame: bool,
+    pub(crate) art_frame_offset: f64,
+    pub(crate) art_frame_size: u32,
+    pub(crate) art_frame_interval: u64,
+    pub(crate) art_thumbnails: bool,
+    pub(crate) art_thumbnails_generate: bool,
+    pub(crate) art_patterns: Globs,
+    pub(crate) art_search_parent: bool,
+    pub(crate) art_size: u32,
+    pub(crate) art_format: Format,
+    pub(crate) art_quality: u8,
+    pub(crate) art_dir: CompactString,
+    pub(crate) art_url: UrlMode,
+    pub(crate) art_http_port: u16,
+    pub(crate) art_fetch: CompactString,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            artist_separators: Separators::new([";", " / ", " feat. ", " ft. "]),
+            album_artist_separators: Separators::new([";", " / ", " feat. ", " ft. "]),
+            composer_separators: Separators::new([";", " / "]),
+            lyricist_separators: Separators::new([";", " / "]),
+            genre_separators: Separators::new([";", "/", ","]),
+            comment_separators: Separators::default(),
+            icy_title_separators: Separators::new([" - ", " – ", " — "]),
+            filename_patterns: Patterns::default(),
+            title_template: CompactString::default(),
+            artist_template: CompactString::default(),
+            album_template: CompactString::default(),
+            seek_threshold: 1.0,
+            signal_rate: 10,
+            settle_timeout: 500,
+            volume_curve: Curve::Linear,
+            volume_zero_mutes: false,
+            stop_mode: StopMode::Rewind,
+            art_cache: true,
+            art_cache_size: 64,
+            art_cache_age: 30,
+            art_frame: true,
+            art_frame_offset: 10.0,
+            art_frame_size: 512,
+            art_frame_interval: 0,
+            art_thumbnails: true,
+            art_thumbnails_generate: false,
+            art_patterns: "cover.*|folder.*|front.*|AlbumArt*.jpg|{basename}.*"
+                .parse()
+                .unwrap_or_default(),
+            art_search_parent: true,
+            art_size: 1024,
+            art_format: Format::Jpeg,
+            art_quality: 90,
+            art_dir: CompactString::default(),
+            art_url: UrlMode::File,
+            art_http_port: 0,
+            art_fetch: CompactString::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Reads `script-opts` entries prefixed with the client name, e.g. `mpris-genre_separators`.
+    pub(crate) async fn load(mpv: &Mpv) -> Result<Self, mpv::Error> {
+        let mut options = Self::default();
+        let name: CompactString = mpv.run_command(mpv::ListCommand::ClientName).await?;
+        let script_opts: BTreeMap<CompactString, CompactString> =
+            mpv.get_property("script-opts").await?;
+        for (key, value) in &script_opts {
+            let Some(key) = key
+                .strip_prefix(name.as_str())
+                .and_then(|key| key.strip_prefix('-'))
+            else {
+                continue;
+            };
+            if let Err(e) = options.set(&key.replace('-', "_"), value) {
+                tracing::error!(error = %e, key = %key, value = %value, "Invalid script option");
+            }
+        }
+        Ok(options)
+    }
+}
+
+derive_deftly_adhoc! {
+    Options:
+
+    impl Options {
+        fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+            match key {
+                $(
+                    stringify!($fname) => self.$fname = OptionValue::parse(value)?,
+                )
+                _ => anyhow::bail!("Unknown option"),
+            }
+            Ok(())
+        }
+    }
+}
+
+trait OptionValue: Sized {
+    fn parse(s: &str) -> anyhow::Result<Self>;
+}
+
+impl OptionValue for bool {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "yes" | "true" => Ok(true),
+            "no" | "false" => Ok(false),
+            _ => anyhow::bail!("Expected yes or no"),
+        }
+    }
+}
+
+macro_rules! option_value_from_str {
+    ($($ty:ty),* $(,)?) => {$(
+        impl OptionValue for $ty {
+            fn parse(s: &str) -> anyhow::Result<Self> {
+                Ok(s.parse()?)
+            }
+        }
+    )*};
+}
+
+option_value_from_str!(
+    u8,
+    u16,
+    u32,
+    u64,
+    f64,
+    CompactString,
+    Separators,
+    Patterns,
+    Globs,
+    Format,
+    UrlMode,
+    Curve,
+    StopMode,
+);
+
+/// A `|`-separated list of separators for multi-valued tags, `\|` being a literal `|`. Matching
+/// ignores ASCII case.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Separators(Vec<CompactString>);
+
+impl Separators {
+    fn new<const N: usize>(separators: [&str; N]) -> Self {
+        Self(separators.into_iter().map(CompactString::from).collect())
+    }
+
+    fn find(&self, s: &str) -> Option<(usize, usize)> {
+        self.0
+            .iter()
+            .filter_map(|sep| {
+                let sep = sep.as_bytes();
+                let index = s
+                    .as_bytes()
+                    .windows(sep.len())
+                    .position(|window| window.eq_ignore_ascii_case(sep))?;
+                Some((index, sep.len()))
+            })
+            .min_by_key(|&(index, len)| (index, Reverse(len)))
+    }
+
+    pub(crate) fn split<'a>(&'a self, mut s: &'a str) -> impl Iterator<Item = &'a str> {
+        iter::from_fn(move || {
+            if s.is_empty() {
+                return None;
+            }
+            Some(match self.find(s) {
+                Some((index, len)) => {
+                    let item = &s[..index];
+                    s = &s[index + len..];
+                    item
+                }
+                None => mem::take(&mut s),
+            })
+        })
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    }
+
+    pub(crate) fn split_once<'a>(&self, s: &'a str) -> Option<(&'a str, &'a str)> {
+        let (index, len) = self.find(s)?;
+        let (left, right) = (s[..index].trim(), s[index + len..].trim());
+        (!left.is_empty() && !right.is_empty()).then_some((left, right))
+    }
+}
+
+impl FromStr for Separators {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut separators = Vec::new();
+        let mut separator = CompactString::default();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => separator.extend(chars.next()),
+                '|' => separators.push(mem::take(&mut separator)),
+                c => separator.push(c),
+            }
+        }
+        separators.push(separator);
+        separators.retain(|sep| !sep.is_empty());
+        Ok(Self(separators))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(separators: &str, s: &str) -> Vec<String> {
+        let separators: Separators = separators.parse().unwrap();
+        separators.split(s).map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn empty_separators_dont_split() {
+        assert_eq!(split("", "a; b"), ["a; b"]);
+        assert_eq!(split("||", "a; b"), ["a; b"]);
+    }
+
+    #[test]
+    fn escaped_separators() {
+        assert_eq!(split(r"\||;", "a|b;c"), ["a", "b", "c"]);
+        assert_eq!(split(r"\\", r"a\b"), ["a", "b"]);
+    }
+
+    #[test]
+    fn multi_char_separators() {
+        let separators = " feat. | / |;";
+        assert_eq!(split(separators, "A FEAT. B / C;D"), ["A", "B", "C", "D"]);
+        assert_eq!(split("/| / ", "A / B/C"), ["A", "B", "C"]);
+        // the longest separator wins at the same position
+        let separators: Separators = "-|--".parse().unwrap();
+        assert_eq!(separators.split_once("A--B"), Some(("A", "B")));
+    }
+
+    #[test]
+    fn split_once_needs_both_sides() {
+        let separators: Separators = " - ".parse().unwrap();
+        assert_eq!(separators.split_once("A - B - C"), Some(("A", "B - C")));
+        assert_eq!(separators.split_once(" - B"), None);
+        assert_eq!(separators.split_once("A"), None);
+    }
+}
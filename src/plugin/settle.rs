@@ -0,0 +1,103 @@
+use super::options::Options;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    Settled,
+    /// Between `start-file` and `file-loaded`.
+    Loading,
+    /// Loaded, waiting a little for art and templates.
+    Waiting,
+}
+
+/// Holds back `Metadata` while a new file loads, so shells see one complete update per track
+/// instead of the filename, then the tags, then the art and the expanded templates.
+pub(super) struct Settle {
+    phase: Phase,
+    /// Whether the art is in, or there is none to wait for.
+    art: bool,
+    /// Whether the templates are expanded, or there are none.
+    expanded: bool,
+    timeout: Duration,
+    timer: Option<smol::Task<()>>,
+    tx: kanal::AsyncSender<()>,
+}
+
+impl Settle {
+    pub(super) fn new(options: &Options) -> (Self, kanal::AsyncReceiver<()>) {
+        let (tx, rx) = kanal::bounded_async(0);
+        let this = Self {
+            phase: Phase::Settled,
+            art: false,
+            expanded: false,
+            timeout: Duration::from_millis(options.settle_timeout),
+            timer: None,
+            tx,
+        };
+        (this, rx)
+    }
+
+    pub(super) fn is_settling(&self) -> bool {
+        self.phase != Phase::Settled
+    }
+
+    pub(super) fn start_file(&mut self) {
+        self.phase = Phase::Loading;
+        self.art = false;
+        self.expanded = false;
+        self.timer = None;
+    }
+
+    fn is_complete(&self) -> bool {
+        self.art && self.expanded
+    }
+
+    /// Returns whether this settles the file, if everything is already in or there is no time to
+    /// wait for it.
+    pub(super) fn file_loaded(&mut self, ex: &smol::LocalExecutor) -> bool {
+        if self.phase != Phase::Loading {
+            return false;
+        }
+        if self.is_complete() || self.timeout.is_zero() {
+            return self.settle();
+        }
+        self.phase = Phase::Waiting;
+        let (tx, timeout) = (self.tx.clone(), self.timeout);
+        self.timer = Some(ex.spawn(async move {
+            smol::Timer::after(timeout).await;
+            _ = tx.send(()).await;
+        }));
+        false
+    }
+
+    /// Art came in, or there is none to wait for. Returns whether this settles the file.
+    pub(super) fn art(&mut self) -> bool {
+        self.art = true;
+        self.phase == Phase::Waiting && self.is_complete() && self.settle()
+    }
+
+    /// The tracks changed, and new art is on its way.
+    pub(super) fn wait_for_art(&mut self) {
+        self.art = false;
+    }
+
+    /// The templates are expanded, or there are none. Returns whether this settles the file.
+    pub(super) fn expanded(&mut self) -> bool {
+        self.expanded = true;
+        self.phase == Phase::Waiting && self.is_complete() && self.settle()
+    }
+
+    /// The properties in the templates changed, and a new expansion is on its way.
+    pub(super) fn wait_for_expansion(&mut self) {
+        self.expanded = false;
+    }
+
+    /// The file ended, loaded or not, or the wait timed out. Returns whether this settles
+    /// the file.
+    pub(super) fn settle(&mut self) -> bool {
+        self.timer = None;
+        let settling = self.is_settling();
+        self.phase = Phase::Settled;
+        settling
+    }
+}
@@ -1,34 +1,52 @@
+use super::{clock::Clock, options::Options, template::Expanded};
 use crate::mpv::{self, Mpv};
 use derive_deftly::{Deftly, derive_deftly_adhoc};
 use enumflags2::{BitFlags, bitflags};
 use smol::lock::RwLock;
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{collections::BTreeMap, path::PathBuf, time::Instant};
 use url::Url;
 
 #[derive(Clone, Debug, Default, Deftly)]
 #[derive_deftly_adhoc]
 pub(crate) struct State {
     pub(crate) fullscreen: bool,
+    pub(crate) vo_configured: bool,
     pub(crate) playlist_entry_id: Option<i64>,
     pub(crate) playlist_current_pos: Option<u64>,
     pub(crate) playlist_count: Option<u64>,
     pub(crate) seekable: bool,
     pub(crate) idle_active: bool,
+    /// Also set while buffering or seeking.
+    pub(crate) core_idle: bool,
     pub(crate) eof_reached: bool,
     pub(crate) pause: bool,
+    /// Set by `Stop` in [`super::mpris::StopMode::Stopped`], until the next `Play`.
+    pub(crate) stopped: bool,
     pub(crate) loop_file: bool,
     pub(crate) loop_playlist: bool,
     pub(crate) speed: f64,
+    /// The `shuffle` option, which `Shuffle` keeps in sync with the queue.
     pub(crate) shuffle: bool,
     pub(crate) volume: f64,
+    pub(crate) volume_max: f64,
+    pub(crate) mute: bool,
     pub(crate) duration: mpv::Seconds,
     pub(crate) media_title: String,
     pub(crate) metadata: BTreeMap<mpv::MetadataKey, String>,
     pub(crate) path: Option<mpv::Path>,
     pub(crate) working_directory: Option<PathBuf>,
     pub(crate) art_url: Option<Url>,
+    pub(crate) ytdl: Option<mpv::YtdlInfo>,
+    pub(crate) chapter: Option<i64>,
+    pub(crate) expanded: Expanded,
     #[deftly(skip)]
+    pub(crate) icy_title_serial: u64,
     pub(crate) track_list: Vec<mpv::Track>,
+    #[deftly(skip)]
+    pub(crate) clock: Clock,
+    /// Bounds of the `speed` option, which don't change.
+    #[deftly(skip)]
+    pub(crate) speed_bounds: (f64, f64),
 }
 
 impl super::Player {
@@ -38,20 +56,33 @@ impl super::Player {
             Ok(Default::default())
         }
 
+        let speed = mpv
+            .get_property::<mpv::OptionInfo>("option-info/speed")
+            .await;
+        let speed =
+            speed.inspect_err(|e| tracing::error!(error = %e, "Failed to get speed bounds"));
+        let speed_bounds = speed.map_or((0.01, 100.0), |speed| {
+            (speed.min.unwrap_or(0.01), speed.max.unwrap_or(100.0))
+        });
         let state = RwLock::new(State {
             fullscreen: property(&mpv, "fullscreen").await?,
+            vo_configured: property(&mpv, "vo-configured").await?,
             playlist_entry_id: None,
             playlist_current_pos: property(&mpv, "playlist-current-pos").await?,
             playlist_count: property(&mpv, "playlist-count").await?,
             seekable: property(&mpv, "seekable").await?,
             idle_active: property(&mpv, "idle-active").await?,
+            core_idle: property(&mpv, "core-idle").await?,
             eof_reached: property(&mpv, "eof-reached").await?,
             pause: property(&mpv, "pause").await?,
+            stopped: property(&mpv, "user-data/mpv-mpris2/stopped").await?,
             loop_file: property(&mpv, "loop-file").await?,
             loop_playlist: property(&mpv, "loop-playlist").await?,
             speed: property(&mpv, "speed").await?,
             shuffle: property(&mpv, "shuffle").await?,
             volume: property(&mpv, "volume").await?,
+            volume_max: property(&mpv, "volume-max").await?,
+            mute: property(&mpv, "mute").await?,
             duration: property(&mpv, "duration").await?,
             media_title: property(&mpv, "media-title").await?,
             metadata: property(&mpv, "metadata").await?,
@@ -59,75 +90,113 @@ impl super::Player {
             path: property(&mpv, "path").await?,
             working_directory: property(&mpv, "working-directory").await?,
             art_url: None,
+            ytdl: property(&mpv, "user-data/mpv/ytdl/json-subprocess-result").await?,
+            chapter: property(&mpv, "chapter").await?,
+            expanded: Default::default(),
+            icy_title_serial: 0,
+            clock: Clock::default(),
+            speed_bounds,
         });
-        Ok(Self { mpv, state })
-    }
-
-    pub(crate) async fn state(&self) -> State {
-        self.state.read().await.clone()
+        let options = Options::load(&mpv).await?;
+        Ok(Self {
+            mpv,
+            state,
+            options,
+        })
     }
 }
 
 impl State {
-    pub(crate) fn change(&mut self, property: mpv::KnownProperty) {
+    /// Applies a property change, returning what actually changed.
+    pub(super) fn change(&mut self, property: mpv::KnownProperty) -> BitFlags<StateDiff> {
         use mpv::KnownProperty;
         match property {
             KnownProperty::Fullscreen(fullscreen) => {
-                self.fullscreen = fullscreen.unwrap_or_default();
-            }
-            KnownProperty::PlaylistCurrentPos(playlist_current_pos) => {
-                self.playlist_current_pos = playlist_current_pos;
+                self.set_fullscreen(fullscreen.unwrap_or_default())
             }
-            KnownProperty::PlaylistCount(playlist_count) => {
-                self.playlist_count = playlist_count;
+            KnownProperty::VoConfigured(vo_configured) => {
+                self.set_vo_configured(vo_configured.unwrap_or_default())
             }
-            KnownProperty::Seekable(seekable) => {
-                self.seekable = seekable.unwrap_or_default();
+            KnownProperty::Chapter(chapter) => self.set_chapter(chapter),
+            KnownProperty::PlaylistCurrentPos(playlist_current_pos) => {
+                self.set_playlist_current_pos(playlist_current_pos)
             }
+            KnownProperty::PlaylistCount(playlist_count) => self.set_playlist_count(playlist_count),
+            KnownProperty::Seekable(seekable) => self.set_seekable(seekable.unwrap_or_default()),
             KnownProperty::IdleActive(idle_active) => {
-                self.idle_active = idle_active.unwrap_or_default();
+                let diff = self.set_idle_active(idle_active.unwrap_or_default());
+                self.sync_clock();
+                diff
+            }
+            KnownProperty::CoreIdle(core_idle) => {
+                let diff = self.set_core_idle(core_idle.unwrap_or_default());
+                self.sync_clock();
+                diff
             }
             KnownProperty::EofReached(eof_reached) => {
-                self.eof_reached = eof_reached.unwrap_or_default();
+                let diff = self.set_eof_reached(eof_reached.unwrap_or_default());
+                self.sync_clock();
+                diff
             }
             KnownProperty::Pause(pause) => {
-                self.pause = pause.unwrap_or_default();
+                let diff = self.set_pause(pause.unwrap_or_default());
+                self.sync_clock();
+                diff
             }
+            KnownProperty::Stopped(stopped) => self.set_stopped(stopped.unwrap_or_default()),
             KnownProperty::LoopFile(loop_file) => {
-                self.loop_file = loop_file.map(bool::from).unwrap_or_default();
+                self.set_loop_file(loop_file.map(bool::from).unwrap_or_default())
             }
             KnownProperty::LoopPlaylist(loop_playlist) => {
-                self.loop_playlist = loop_playlist.map(bool::from).unwrap_or_default();
+                self.set_loop_playlist(loop_playlist.map(bool::from).unwrap_or_default())
             }
             KnownProperty::Speed(speed) => {
-                self.speed = speed.unwrap_or_default();
+                let diff = self.set_speed(speed.unwrap_or_default());
+                self.sync_clock();
+                diff
             }
-            KnownProperty::Shuffle(shuffle) => {
-                self.shuffle = shuffle.unwrap_or_default();
-            }
-            KnownProperty::Volume(volume) => {
-                self.volume = volume.unwrap_or_default();
-            }
-            KnownProperty::Duration(duration) => {
-                self.duration = duration.unwrap_or_default();
+            KnownProperty::Shuffle(shuffle) => self.set_shuffle(shuffle.unwrap_or_default()),
+            KnownProperty::Volume(volume) => self.set_volume(volume.unwrap_or_default()),
+            KnownProperty::VolumeMax(volume_max) => {
+                self.set_volume_max(volume_max.unwrap_or_default())
             }
+            KnownProperty::Mute(mute) => self.set_mute(mute.unwrap_or_default()),
+            KnownProperty::Duration(duration) => self.set_duration(duration.unwrap_or_default()),
             KnownProperty::MediaTitle(media_title) => {
-                self.media_title = media_title.unwrap_or_default();
+                self.set_media_title(media_title.unwrap_or_default())
             }
             KnownProperty::Metadata(metadata) => {
-                self.metadata = metadata;
-            }
-            KnownProperty::TrackList(track_list) => {
-                self.track_list = track_list;
-            }
-            KnownProperty::Path(path) => {
-                self.path = path;
+                let icy_title = &mpv::MetadataKey::IcyTitle;
+                if metadata.get(icy_title) != self.metadata.get(icy_title) {
+                    self.icy_title_serial += 1;
+                }
+                self.set_metadata(metadata)
             }
+            KnownProperty::TrackList(track_list) => self.set_track_list(track_list),
+            KnownProperty::Path(path) => self.set_path(path),
             KnownProperty::WorkingDirectory(working_directory) => {
-                self.working_directory = working_directory;
+                self.set_working_directory(working_directory)
+            }
+            KnownProperty::YtdlJsonSubprocessResult(result) => {
+                self.set_ytdl(result.and_then(|result| {
+                    serde_json::from_str(&result.stdout)
+                        .inspect_err(|e| tracing::error!(error = %e, "Failed to parse ytdl JSON"))
+                        .ok()
+                }))
             }
         }
     }
+
+    /// Whether playback advances, not while paused or buffering.
+    pub(super) fn playing(&self) -> bool {
+        !self.pause && !self.idle_active && !self.eof_reached && !self.core_idle
+    }
+
+    /// The clock only advances while playing.
+    fn sync_clock(&mut self) {
+        let rate = if self.playing() { self.speed } else { 0.0 };
+        self.clock.set_rate(rate, Instant::now());
+    }
 }
 
 derive_deftly_adhoc! {
@@ -144,15 +213,15 @@ derive_deftly_adhoc! {
     }
 
     impl State {
-        pub(super) fn diff(&self, other: &Self) -> BitFlags<StateDiff> {
-            let mut diff = BitFlags::empty();
-            $(
-                ${when not(fmeta(skip))}
-                if self.$fname != other.$fname {
-                    diff |= StateDiff::${pascal_case $fname};
+        $(
+            ${when not(fmeta(skip))}
+            pub(super) fn ${paste set_ $fname}(&mut self, value: $ftype) -> BitFlags<StateDiff> {
+                if self.$fname == value {
+                    return BitFlags::empty();
                 }
-            )
-            diff
-        }
+                self.$fname = value;
+                StateDiff::${pascal_case $fname}.into()
+            }
+        )
     }
 }
@@ -0,0 +1,69 @@
+use super::options::Options;
+use crate::mpv::{self, Mpv};
+use compact_str::CompactString;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Expanded {
+    pub(crate) title: Option<String>,
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+}
+
+pub(super) struct State {
+    task: Option<smol::Task<()>>,
+    templates: [CompactString; 3],
+    tx: kanal::AsyncSender<Expanded>,
+}
+
+impl State {
+    pub(super) fn new(options: &Options) -> (Self, kanal::AsyncReceiver<Expanded>) {
+        let (tx, rx) = kanal::bounded_async(0);
+        let this = Self {
+            task: None,
+            templates: [
+                options.title_template.clone(),
+                options.artist_template.clone(),
+                options.album_template.clone(),
+            ],
+            tx,
+        };
+        (this, rx)
+    }
+
+    /// Returns whether an expansion is on its way, not without templates.
+    pub(super) fn spawn_worker(&mut self, ex: &smol::LocalExecutor, mpv: Mpv) -> bool {
+        if self.templates.iter().all(CompactString::is_empty) {
+            return false;
+        }
+        let task = worker(self.tx.clone(), mpv, self.templates.clone());
+        self.task = Some(ex.spawn(task));
+        true
+    }
+}
+
+async fn worker(tx: kanal::AsyncSender<Expanded>, mpv: Mpv, templates: [CompactString; 3]) {
+    async fn expand(mpv: &Mpv, template: CompactString) -> Option<String> {
+        if template.is_empty() {
+            return None;
+        }
+        let cmd = mpv::NamedCommand::ExpandText { text: template };
+        match mpv.run_command::<String>(cmd).await {
+            Ok(text) if text.trim().is_empty() => None,
+            Ok(text) => Some(text),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to expand template");
+                None
+            }
+        }
+    }
+
+    let [title, artist, album] = templates;
+    let expanded = Expanded {
+        title: expand(&mpv, title).await,
+        artist: expand(&mpv, artist).await,
+        album: expand(&mpv, album).await,
+    };
+    if let Err(e) = tx.send(expanded).await {
+        tracing::error!(error = %e, "Failed to send expanded templates");
+    }
+}
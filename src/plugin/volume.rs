@@ -0,0 +1,68 @@
+use strum::EnumString;
+
+/// How MPRIS volumes from 0 to 1 map onto mpv's, from 0 to `volume-max`. mpv's volume is already
+/// cubic, its gain being the cube of the percentage.
+#[derive(Clone, Copy, Debug, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub(crate) enum Curve {
+    /// Proportional to mpv's volume.
+    Linear,
+    /// Proportional to the gain.
+    Cubic,
+    /// Proportional to the gain in dB, over a range of 60 dB.
+    Db,
+}
+
+impl Curve {
+    /// mpv's volume for an MPRIS one.
+    pub(crate) fn to_mpv(self, volume: f64, max: f64) -> f64 {
+        let max = ceiling(max);
+        let volume = volume.clamp(0.0, 1.0);
+        let fraction = match self {
+            Self::Linear => volume,
+            Self::Cubic => volume.cbrt(),
+            // 60 dB of gain is 3 decades, the cube root of the gain is 1 decade
+            Self::Db if volume > 0.0 => 10f64.powf(volume - 1.0),
+            Self::Db => 0.0,
+        };
+        fraction * max
+    }
+
+    /// The MPRIS volume for mpv's.
+    pub(crate) fn to_mpris(self, volume: f64, max: f64) -> f64 {
+        let fraction = (volume / ceiling(max)).clamp(0.0, 1.0);
+        match self {
+            Self::Linear => fraction,
+            Self::Cubic => fraction.powi(3),
+            Self::Db if fraction > 0.0 => (1.0 + fraction.log10()).max(0.0),
+            Self::Db => 0.0,
+        }
+    }
+}
+
+/// `volume-max`, or the lowest it can be until it is known, which is below mpv's default of 130 so
+/// that no volume goes past the actual maximum.
+fn ceiling(max: f64) -> f64 {
+    if max > 0.0 { max } else { 100.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_max_stays_within_any_max() {
+        for curve in [Curve::Linear, Curve::Cubic, Curve::Db] {
+            assert_eq!(curve.to_mpv(1.0, 0.0), 100.0);
+            assert_eq!(curve.to_mpris(130.0, 0.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn full_volume_is_volume_max() {
+        for curve in [Curve::Linear, Curve::Cubic, Curve::Db] {
+            assert_eq!(curve.to_mpv(1.0, 130.0), 130.0);
+            assert_eq!(curve.to_mpris(130.0, 130.0), 1.0);
+        }
+    }
+}
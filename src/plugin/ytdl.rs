@@ -0,0 +1,137 @@
+use crate::mpv;
+use url::Url;
+
+impl mpv::YtdlInfo {
+    /// The ytdl JSON outlives the file it was fetched for, so check that it belongs to `path`.
+    pub(super) fn matches(&self, path: &Option<mpv::Path>) -> bool {
+        let Some(mpv::Path::Url(path)) = path else {
+            return false;
+        };
+        [&self.original_url, &self.webpage_url]
+            .into_iter()
+            .flatten()
+            .filter_map(|url| Url::parse(url).ok())
+            .any(|url| url == *path)
+    }
+
+    pub(super) fn artist(&self) -> Option<&str> {
+        self.uploader.as_deref().or(self.channel.as_deref())
+    }
+
+    pub(super) fn url(&self) -> Option<Url> {
+        Url::parse(self.webpage_url.as_deref()?).ok()
+    }
+
+    /// `upload_date` is `YYYYMMDD`, xesam wants ISO 8601.
+    pub(super) fn content_created(&self) -> Option<String> {
+        let date = self.upload_date.as_deref()?;
+        if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let (year, rest) = date.split_at(4);
+        let (month, day) = rest.split_at(2);
+        Some(format!("{year}-{month}-{day}T00:00:00Z"))
+    }
+
+    /// JPEG and PNG first, which don't need converting, then the preferred and largest one.
+    pub(super) fn art_url(&self) -> Option<Url> {
+        self.thumbnails
+            .iter()
+            .max_by_key(|thumbnail| {
+                let url = thumbnail.url.to_ascii_lowercase();
+                let path = url.split(['?', '#']).next().unwrap_or_default();
+                let compatible = [".jpg", ".jpeg", ".png"]
+                    .iter()
+                    .any(|suffix| path.ends_with(suffix));
+                let pixels = thumbnail.width.zip(thumbnail.height).map(|(w, h)| w * h);
+                (compatible, thumbnail.preference, pixels)
+            })
+            .map(|thumbnail| thumbnail.url.as_str())
+            .or(self.thumbnail.as_deref())
+            .and_then(|url| Url::parse(url).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mpv::{self, YtdlInfo};
+    use std::path::PathBuf;
+    use url::Url;
+
+    const FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/ytdl.json"
+    ));
+
+    fn info() -> YtdlInfo {
+        serde_json::from_str(FIXTURE).unwrap()
+    }
+
+    fn url(url: &str) -> Option<mpv::Path> {
+        Some(mpv::Path::Url(Url::parse(url).unwrap()))
+    }
+
+    #[test]
+    fn parse() {
+        let info = info();
+        assert_eq!(info.uploader.as_deref(), Some("Rick Astley"));
+        assert_eq!(info.upload_date.as_deref(), Some("20091025"));
+        assert_eq!(info.thumbnails.len(), 5);
+        assert_eq!(info.thumbnails[0].width, None);
+        assert_eq!(info.thumbnails[1].preference, Some(-12));
+    }
+
+    #[test]
+    fn matches() {
+        let info = info();
+        assert!(info.matches(&url("https://youtu.be/dQw4w9WgXcQ")));
+        assert!(info.matches(&url("https://www.youtube.com/watch?v=dQw4w9WgXcQ")));
+        assert!(!info.matches(&url("https://www.youtube.com/watch?v=oHg5SJYRHA0")));
+        assert!(!info.matches(&Some(mpv::Path::Path(PathBuf::from("/tmp/a.mkv")))));
+        assert!(!info.matches(&None));
+    }
+
+    #[test]
+    fn artist() {
+        let mut info = info();
+        assert_eq!(info.artist(), Some("Rick Astley"));
+        info.uploader = None;
+        assert_eq!(info.artist(), Some("Rick Astley Official"));
+    }
+
+    #[test]
+    fn content_created() {
+        let mut info = info();
+        assert_eq!(
+            info.content_created().as_deref(),
+            Some("2009-10-25T00:00:00Z")
+        );
+        for date in ["2009102", "2009-10-25", "20091O25"] {
+            info.upload_date = Some(date.into());
+            assert_eq!(info.content_created(), None, "{date}");
+        }
+    }
+
+    #[test]
+    fn art_url() {
+        let mut info = info();
+        // JPEG first, then highest preference, then most pixels
+        assert_eq!(
+            info.art_url().unwrap().as_str(),
+            "https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg"
+        );
+        info.thumbnails
+            .retain(|thumbnail| thumbnail.url.ends_with(".webp"));
+        assert_eq!(
+            info.art_url().unwrap().as_str(),
+            "https://i.ytimg.com/vi_webp/dQw4w9WgXcQ/hqdefault.webp"
+        );
+        info.thumbnails.clear();
+        assert_eq!(
+            info.art_url().unwrap().as_str(),
+            "https://i.ytimg.com/vi/dQw4w9WgXcQ/maxresdefault.jpg"
+        );
+        info.thumbnail = None;
+        assert_eq!(info.art_url(), None);
+    }
+}